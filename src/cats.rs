@@ -0,0 +1,49 @@
+use crate::{
+    object::{ObjectId, ObjectInfo},
+    world::{Edit, World},
+};
+
+impl World {
+    /// Every cat in the level, in reading order
+    pub fn cats(&self) -> Vec<ObjectId> {
+        self.cells_iterator()
+            .flat_map(|point| {
+                self[point]
+                    .iter()
+                    .filter(|v| v.obj_type == ObjectInfo::Cat)
+                    .map(|v| v.id)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+    /// The only cat that moves in levels where the player switches between cats,
+    /// or None if every cat moves. Falls back to the first cat if the selected one is gone.
+    pub fn active_cat(&self) -> Option<ObjectId> {
+        if !self.switch_cats {
+            return None;
+        }
+        let cats = self.cats();
+        match self.selected_cat {
+            Some(id) if cats.contains(&id) => Some(id),
+            _ => cats.first().copied(),
+        }
+    }
+    /// Make the next cat in reading order the active one.
+    /// Switching is not a move, so it is not logged in history.
+    pub fn switch_cat(&mut self) {
+        let Some(active) = self.active_cat() else {
+            return;
+        };
+        let cats = self.cats();
+        let idx = cats.iter().position(|v| *v == active).unwrap_or(0);
+        self.selected_cat = Some(cats[(idx + 1) % cats.len()]);
+    }
+    /// Keep the selection on a cat that the game replaced with a new object, and log it in history
+    pub fn replace_cat(&mut self, old: ObjectId, new: ObjectId) {
+        if self.active_cat() == Some(old) {
+            self.edit_history
+                .push((self.move_id, Edit::SelectCat(self.selected_cat)));
+            self.selected_cat = Some(new);
+        }
+    }
+}
@@ -0,0 +1,977 @@
+use turbo::*;
+
+use crate::{
+    level_file::LevelFile,
+    levels::{PORTAL_BLUE, PORTAL_GREEN, PORTAL_ORANGE, PORTAL_PURPLE, WinRequirement},
+    menu::{button, text_window},
+    object::ObjectInfo,
+    render::CHANNELS,
+    robot::Roam,
+    signal::Gate,
+    solver::{DEFAULT_STATE_LIMIT, STATES_PER_FRAME, Search, Solution},
+    util::{Direction, Point},
+    validate::{Problem, validate},
+    world::World,
+};
+
+const PORTAL_COLORS: [u32; 4] = [PORTAL_ORANGE, PORTAL_BLUE, PORTAL_PURPLE, PORTAL_GREEN];
+/// Largest width or height the editor allows
+const MAX_SIZE: usize = 9;
+/// Most moves between fire spreading that the editor offers
+const MAX_FIRE_SPREAD: usize = 5;
+/// Left edge of the settings panel; the grid can be clicked anywhere left of it
+const PANEL_X: i32 = 372;
+
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
+pub enum Tool {
+    Floor,
+    Erase,
+    Link,
+    Power,
+    Cat,
+    Goal,
+    Box,
+    Water,
+    Trap,
+    Acid,
+    Fire,
+    FireOut,
+    BurntBox,
+    Door,
+    PushButton,
+    ToggleButton,
+    Conveyor,
+    RotatingConveyor,
+    Portal,
+    Barrier,
+    WallBack,
+    WallFront,
+    WallLeft,
+    WallRight,
+    PushSwitch,
+    ToggleSwitch,
+    Gate,
+    Delay,
+    Latch,
+    Relay,
+    Laser,
+    Mirror,
+    RotatingMirror,
+    LaserReceiver,
+    Ice,
+    Key,
+    LockedDoor,
+    OneWay,
+    Pit,
+    Robot,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 40] = [
+        Tool::Floor,
+        Tool::Erase,
+        Tool::Link,
+        Tool::Power,
+        Tool::Cat,
+        Tool::Goal,
+        Tool::Box,
+        Tool::Water,
+        Tool::Trap,
+        Tool::Acid,
+        Tool::Fire,
+        Tool::FireOut,
+        Tool::BurntBox,
+        Tool::Door,
+        Tool::PushButton,
+        Tool::ToggleButton,
+        Tool::Conveyor,
+        Tool::RotatingConveyor,
+        Tool::Portal,
+        Tool::Barrier,
+        Tool::WallBack,
+        Tool::WallFront,
+        Tool::WallLeft,
+        Tool::WallRight,
+        Tool::PushSwitch,
+        Tool::ToggleSwitch,
+        Tool::Gate,
+        Tool::Delay,
+        Tool::Latch,
+        Tool::Relay,
+        Tool::Laser,
+        Tool::Mirror,
+        Tool::RotatingMirror,
+        Tool::LaserReceiver,
+        Tool::Ice,
+        Tool::Key,
+        Tool::LockedDoor,
+        Tool::OneWay,
+        Tool::Pit,
+        Tool::Robot,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Floor => "Floor",
+            Tool::Erase => "Erase",
+            Tool::Link => "Link wires",
+            Tool::Power => "Power wire",
+            Tool::Cat => "Cat",
+            Tool::Goal => "Goal",
+            Tool::Box => "Box",
+            Tool::Water => "Water",
+            Tool::Trap => "Mouse trap",
+            Tool::Acid => "Acid",
+            Tool::Fire => "Fire",
+            Tool::FireOut => "Charcoal",
+            Tool::BurntBox => "Smoke",
+            Tool::Door => "Door",
+            Tool::PushButton => "Push button",
+            Tool::ToggleButton => "Toggle button",
+            Tool::Conveyor => "Conveyor",
+            Tool::RotatingConveyor => "Rotating conv.",
+            Tool::Portal => "Portal",
+            Tool::Barrier => "Barrier",
+            Tool::WallBack => "Back wall",
+            Tool::WallFront => "Front wall",
+            Tool::WallLeft => "Left wall",
+            Tool::WallRight => "Right wall",
+            Tool::PushSwitch => "Push switch",
+            Tool::ToggleSwitch => "Toggle switch",
+            Tool::Gate => "Logic gate",
+            Tool::Delay => "Delay",
+            Tool::Latch => "Latch",
+            Tool::Relay => "Relay",
+            Tool::Laser => "Laser",
+            Tool::Mirror => "Mirror",
+            Tool::RotatingMirror => "Rotating mirror",
+            Tool::LaserReceiver => "Laser receiver",
+            Tool::Ice => "Ice",
+            Tool::Key => "Key",
+            Tool::LockedDoor => "Locked door",
+            Tool::OneWay => "One-way gate",
+            Tool::Pit => "Pit",
+            Tool::Robot => "Robot",
+        }
+    }
+    fn uses_direction(&self) -> bool {
+        matches!(
+            self,
+            Tool::Door
+                | Tool::Conveyor
+                | Tool::RotatingConveyor
+                | Tool::Laser
+                | Tool::LockedDoor
+                | Tool::OneWay
+                | Tool::Robot
+        )
+    }
+    fn uses_flag(&self) -> bool {
+        matches!(
+            self,
+            Tool::Door
+                | Tool::Conveyor
+                | Tool::RotatingConveyor
+                | Tool::Portal
+                | Tool::Mirror
+                | Tool::RotatingMirror
+                | Tool::WallBack
+                | Tool::WallLeft
+                | Tool::WallRight
+        )
+    }
+    fn uses_wire(&self) -> bool {
+        matches!(
+            self,
+            Tool::PushButton | Tool::ToggleButton | Tool::Power | Tool::Relay | Tool::LaserReceiver
+        )
+    }
+    fn uses_color(&self) -> bool {
+        matches!(self, Tool::Portal | Tool::Key | Tool::LockedDoor)
+    }
+    /// Labels of the channels the tool's object uses, in the order of Editor::channels
+    fn channel_labels(&self, gate: Gate) -> &'static [&'static str] {
+        match self {
+            Tool::PushSwitch | Tool::ToggleSwitch | Tool::Relay => &["Channel"],
+            Tool::Gate if gate == Gate::Not => &["Output", "Input"],
+            Tool::Gate => &["Output", "Input", "Input"],
+            Tool::Delay => &["Output", "Input"],
+            Tool::Latch => &["Output", "Set", "Reset"],
+            _ => &[],
+        }
+    }
+}
+
+/// Which text is being typed into
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
+pub enum TextField {
+    Caption,
+    Hint,
+}
+
+/// Window drawn over the editor
+#[turbo::serialize]
+#[derive(PartialEq)]
+enum Popup {
+    /// Shows the level file of the level being edited
+    Export(String),
+    /// Text box for typing or pasting in a level file
+    Import { text: String, error: String },
+}
+
+impl Popup {
+    /// Draw the popup and handle typing.
+    /// Returns true once it is closed, along with the level if one was imported.
+    fn run(&mut self) -> (bool, Option<LevelFile>) {
+        let (text, error) = match self {
+            Popup::Export(text) => return (text_window("Level file", text), None),
+            Popup::Import { text, error } => (text, error),
+        };
+        let keyboard = keyboard::get();
+        if keyboard.backspace().just_pressed() {
+            text.pop();
+        }
+        // Level files span several lines, so enter types a newline
+        text.extend(
+            keyboard
+                .text()
+                .chars()
+                .filter(|c| *c == '\n' || !c.is_control()),
+        );
+        if text_window("Type or paste a level file", text) {
+            return (true, None);
+        }
+        text_box!(
+            error.as_str(),
+            bounds = Bounds::with_size(360, 12)
+                .anchor_center(&turbo::screen())
+                .translate_y(112),
+            align = "center",
+            fixed = true,
+            color = 0xBC4040FF,
+        );
+        let import_bounds = Bounds::with_size(50, 20)
+            .anchor_center(&turbo::screen())
+            .translate_y(90)
+            .translate_x(-60);
+        if !button("Import", import_bounds, 0x777777FF, 0x888888FF) || text.is_empty() {
+            return (false, None);
+        }
+        match LevelFile::parse(text) {
+            Ok(level) if level.width > MAX_SIZE || level.height > MAX_SIZE => {
+                *error = format!("Levels can be at most {0} by {0}", MAX_SIZE);
+                (false, None)
+            }
+            Ok(level) => (true, Some(level)),
+            Err(err) => {
+                *error = format!("{}", err);
+                (false, None)
+            }
+        }
+    }
+}
+
+#[turbo::serialize]
+pub struct Editor {
+    /// Level being edited
+    pub level: LevelFile,
+    /// World built from the level, only used for drawing
+    preview: World,
+    /// Problems found in the preview
+    problems: Vec<Problem>,
+    tool: Tool,
+    /// Direction of doors and conveyors
+    direction: Direction,
+    /// Second direction of rotating conveyors
+    alt_direction: Direction,
+    /// Open doors, powered conveyors, open portals, / mirrors, or short walls
+    flag: bool,
+    /// Wire that buttons and the power tool use
+    wire_idx: usize,
+    /// Index into PORTAL_COLORS, used for portals, keys, and locked doors
+    color: usize,
+    /// Indices into CHANNELS of the channels signal objects use
+    channels: [usize; 3],
+    /// Logic of placed gates
+    gate: Gate,
+    /// Rule placed robots move by
+    roam: Roam,
+    /// Index into level.objects of the button or portal waiting for its target to be clicked
+    linking: Option<usize>,
+    /// Text field that keyboard input goes to
+    typing: Option<TextField>,
+    /// Window covering the editor, which takes all input while open
+    popup: Option<Popup>,
+    /// Level as it was when Find par was pressed, while its par is being searched for
+    par_level: Option<LevelFile>,
+    /// Search for the par of par_level, run a little each frame.
+    /// Left out of the saved state, so it starts over after a hot reload.
+    #[borsh(skip)]
+    #[serde(skip)]
+    par_search: Option<Search>,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let level = LevelFile {
+            width: 5,
+            height: 5,
+            floors: vec![vec![true; 5]; 5],
+            requirements: vec![WinRequirement::CatsInGoals(1)],
+            caption: String::new(),
+            hint: String::new(),
+            par: None,
+            hide_wiring: false,
+            fire_spread: None,
+            switch_cats: false,
+            objects: vec![],
+            wiring: vec![],
+            channels: vec![],
+        };
+        let preview = level.build();
+        Self {
+            problems: validate(&preview),
+            preview,
+            level,
+            tool: Tool::Floor,
+            direction: Direction::East,
+            alt_direction: Direction::North,
+            flag: false,
+            wire_idx: 0,
+            color: 0,
+            channels: [0, 1, 2],
+            gate: Gate::And,
+            roam: Roam::Patrol,
+            linking: None,
+            typing: None,
+            popup: None,
+            par_level: None,
+            par_search: None,
+        }
+    }
+    /// Rebuild the preview after the level changes
+    fn refresh(&mut self) {
+        self.preview = self.level.build();
+        self.problems = validate(&self.preview);
+    }
+    /// Draw the editor and handle input. Returns true if the level should be playtested.
+    pub fn run(&mut self) -> bool {
+        let center = World::to_screen_space(
+            (self.level.width as i32 - 1, self.level.height as i32 - 1).into(),
+        );
+        camera::set_xy(center.0 / 2 + 90, center.1 / 2 + 4);
+        // Show where missing floors are so they can be clicked
+        for point in self.preview.cells_iterator() {
+            if !self.level.floors[point.y() as usize][point.x() as usize] {
+                let pos = World::to_screen_space(point);
+                sprite!("factory/floor3", x = pos.0, y = pos.1, opacity = 0.25);
+            }
+        }
+        self.preview.draw_cells();
+        self.draw_links();
+        if self.popup.is_some() {
+            self.run_popup();
+            return false;
+        }
+        self.grid_input();
+        self.text_input();
+        let play = self.panel();
+        text_box!(
+            self.level.caption.as_str(),
+            bounds = Bounds::with_size(350, 40)
+                .anchor_bottom(&turbo::screen())
+                .translate_x(10),
+            align = "center",
+            fixed = true,
+        );
+        play
+    }
+    /// Draw the open popup and handle its input
+    fn run_popup(&mut self) {
+        let Some(popup) = &mut self.popup else {
+            return;
+        };
+        let (closed, level) = popup.run();
+        if let Some(level) = level {
+            self.level = level;
+            self.linking = None;
+            self.typing = None;
+            self.refresh();
+        }
+        if closed {
+            self.popup = None;
+        }
+    }
+    /// Draw lines from buttons and portals to what they are wired to
+    fn draw_links(&self) {
+        let center = |point: Point| {
+            let pos = World::to_screen_space(point);
+            (pos.0 + 26, pos.1 + 14)
+        };
+        for (i, (point, obj, _)) in self.level.objects.iter().enumerate() {
+            let color = if self.linking == Some(i) {
+                0xFFFFFFFF
+            } else {
+                0xCBB41CAA
+            };
+            match obj {
+                ObjectInfo::PushButton(dst, _)
+                | ObjectInfo::ToggleButton(dst, _)
+                | ObjectInfo::LaserReceiver(dst, _) => {
+                    path!(
+                        start = center(*point),
+                        end = center(*dst),
+                        size = 2,
+                        color = color
+                    );
+                }
+                ObjectInfo::Portal(ends, _, portal_color) => {
+                    for end in ends.iter() {
+                        path!(
+                            start = center(*point),
+                            end = center(*end),
+                            size = 1,
+                            color = *portal_color
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(i) = self.linking {
+            let mouse = pointer::world();
+            path!(
+                start = center(self.level.objects[i].0),
+                end = (mouse.x, mouse.y),
+                size = 1,
+                color = 0xFFFFFFFF
+            );
+        }
+    }
+    /// Handle clicks on the grid
+    fn grid_input(&mut self) {
+        let screen = pointer::screen();
+        if !screen.just_pressed() || screen.x >= PANEL_X || screen.y < 24 {
+            return;
+        }
+        let mouse = pointer::world();
+        let point = World::from_screen_space(mouse.x, mouse.y);
+        if !self.preview.point_inside(point) {
+            self.linking = None;
+            return;
+        }
+        self.typing = None;
+        if let Some(i) = self.linking {
+            self.link(i, point);
+            self.refresh();
+            return;
+        }
+        match self.tool {
+            Tool::Floor => {
+                let floor = &mut self.level.floors[point.y() as usize][point.x() as usize];
+                *floor = !*floor;
+            }
+            Tool::Erase => {
+                if let Some(i) = self.level.objects.iter().rposition(|v| v.0 == point) {
+                    self.level.objects.remove(i);
+                }
+            }
+            Tool::Link => {
+                self.linking = self.level.objects.iter().rposition(|v| {
+                    v.0 == point
+                        && matches!(
+                            v.1,
+                            ObjectInfo::PushButton(..)
+                                | ObjectInfo::ToggleButton(..)
+                                | ObjectInfo::LaserReceiver(..)
+                                | ObjectInfo::Portal(..)
+                        )
+                });
+            }
+            Tool::Power => {
+                let wire = (point, self.wire_idx);
+                if let Some(i) = self.level.wiring.iter().position(|v| *v == wire) {
+                    self.level.wiring.remove(i);
+                } else {
+                    self.level.wiring.push(wire);
+                }
+            }
+            _ => {
+                let obj = self.make_object(point);
+                // Open doors have to start with the open animation
+                let anim = if obj == ObjectInfo::Door(self.direction, true) {
+                    2
+                } else {
+                    0
+                };
+                let links = matches!(
+                    obj,
+                    ObjectInfo::PushButton(..)
+                        | ObjectInfo::ToggleButton(..)
+                        | ObjectInfo::LaserReceiver(..)
+                        | ObjectInfo::Portal(..)
+                );
+                self.level.objects.push((point, obj, anim));
+                if links {
+                    self.linking = Some(self.level.objects.len() - 1);
+                }
+            }
+        }
+        self.refresh();
+    }
+    /// Point the button or portal at index i toward the clicked point
+    fn link(&mut self, i: usize, point: Point) {
+        let (source, obj, _) = &mut self.level.objects[i];
+        match obj {
+            ObjectInfo::PushButton(dst, _)
+            | ObjectInfo::ToggleButton(dst, _)
+            | ObjectInfo::LaserReceiver(dst, _) => {
+                *dst = point;
+                self.linking = None;
+            }
+            ObjectInfo::Portal(ends, _, _) => {
+                // Portals keep linking until their own cell is clicked
+                if point == *source {
+                    self.linking = None;
+                } else if let Some(j) = ends.iter().position(|v| *v == point) {
+                    ends.remove(j);
+                } else {
+                    ends.push(point);
+                }
+            }
+            _ => self.linking = None,
+        }
+    }
+    /// Object that the current tool places
+    fn make_object(&self, point: Point) -> ObjectInfo {
+        match self.tool {
+            Tool::Cat => ObjectInfo::Cat,
+            Tool::Goal => ObjectInfo::Goal,
+            Tool::Box => ObjectInfo::Box,
+            Tool::Water => ObjectInfo::Water,
+            Tool::Trap => ObjectInfo::Trap,
+            Tool::Acid => ObjectInfo::Death,
+            Tool::Fire => ObjectInfo::Fire,
+            Tool::FireOut => ObjectInfo::FireOut,
+            Tool::BurntBox => ObjectInfo::BurntBox,
+            Tool::Door => ObjectInfo::Door(self.direction, self.flag),
+            Tool::PushButton => ObjectInfo::PushButton(point, self.wire_idx),
+            Tool::ToggleButton => ObjectInfo::ToggleButton(point, self.wire_idx),
+            Tool::Conveyor => ObjectInfo::ToggleableConveyor(self.direction, self.flag),
+            Tool::RotatingConveyor => {
+                ObjectInfo::RotateableConveyor(self.direction, self.alt_direction, self.flag)
+            }
+            Tool::Portal => ObjectInfo::Portal(vec![], self.flag, PORTAL_COLORS[self.color]),
+            Tool::WallBack => ObjectInfo::WallBack(self.flag),
+            Tool::WallFront => ObjectInfo::WallFront,
+            Tool::WallLeft => ObjectInfo::WallLeft(self.flag),
+            Tool::WallRight => ObjectInfo::WallRight(self.flag),
+            Tool::PushSwitch => ObjectInfo::PushSwitch(self.channel(0)),
+            Tool::ToggleSwitch => ObjectInfo::ToggleSwitch(self.channel(0)),
+            Tool::Gate => {
+                let inputs = if self.gate == Gate::Not {
+                    vec![self.channel(1)]
+                } else {
+                    vec![self.channel(1), self.channel(2)]
+                };
+                ObjectInfo::Gate(self.gate, inputs, self.channel(0))
+            }
+            Tool::Delay => ObjectInfo::Delay(self.channel(1), self.channel(0), false, false),
+            Tool::Latch => {
+                ObjectInfo::Latch(self.channel(1), self.channel(2), self.channel(0), false)
+            }
+            Tool::Relay => ObjectInfo::Relay(self.channel(0), self.wire_idx),
+            Tool::Laser => ObjectInfo::Laser(self.direction),
+            Tool::Mirror => ObjectInfo::Mirror(self.flag),
+            Tool::RotatingMirror => ObjectInfo::RotateableMirror(self.flag, false),
+            Tool::LaserReceiver => ObjectInfo::LaserReceiver(point, self.wire_idx),
+            Tool::Ice => ObjectInfo::Ice,
+            Tool::OneWay => ObjectInfo::OneWay(self.direction),
+            Tool::Pit => ObjectInfo::Pit(false),
+            Tool::Robot => ObjectInfo::Robot(self.roam, self.direction),
+            Tool::Key => ObjectInfo::Key(PORTAL_COLORS[self.color]),
+            Tool::LockedDoor => ObjectInfo::LockedDoor(self.direction, PORTAL_COLORS[self.color]),
+            Tool::Barrier | Tool::Floor | Tool::Erase | Tool::Link | Tool::Power => {
+                ObjectInfo::Barrier
+            }
+        }
+    }
+    /// Name of the channel picked in slot i
+    fn channel(&self, i: usize) -> String {
+        CHANNELS[self.channels[i]].0.to_string()
+    }
+    /// Type into the caption or hint
+    fn text_input(&mut self) {
+        let Some(field) = self.typing else {
+            return;
+        };
+        let keyboard = keyboard::get();
+        let text = match field {
+            TextField::Caption => &mut self.level.caption,
+            TextField::Hint => &mut self.level.hint,
+        };
+        if keyboard.backspace().just_pressed() {
+            text.pop();
+        }
+        text.push_str(&keyboard.text());
+        if keyboard.enter().just_pressed() || keyboard.escape().just_pressed() {
+            self.typing = None;
+        }
+    }
+    /// Change the size of the level, keeping everything that still fits
+    fn resize(&mut self, width: usize, height: usize) {
+        let width = width.clamp(1, MAX_SIZE);
+        let height = height.clamp(1, MAX_SIZE);
+        let inside = |p: &Point| p.x() < width as i32 && p.y() < height as i32;
+        self.level.floors.resize(height, vec![true; width]);
+        for row in self.level.floors.iter_mut() {
+            row.resize(width, true);
+        }
+        self.level.objects.retain(|v| inside(&v.0));
+        self.level.wiring.retain(|v| inside(&v.0));
+        for (point, obj, _) in self.level.objects.iter_mut() {
+            match obj {
+                ObjectInfo::PushButton(dst, _)
+                | ObjectInfo::ToggleButton(dst, _)
+                | ObjectInfo::LaserReceiver(dst, _)
+                    if !inside(dst) =>
+                {
+                    *dst = *point;
+                }
+                ObjectInfo::Portal(ends, _, _) => ends.retain(inside),
+                _ => {}
+            }
+        }
+        self.level.width = width;
+        self.level.height = height;
+        self.linking = None;
+        self.refresh();
+    }
+    /// Search a little further for the par, setting it once the search finishes.
+    /// Returns the text to show next to the Find par button.
+    fn run_par_search(&mut self) -> String {
+        // Editing the level makes the search out of date
+        if self
+            .par_level
+            .as_ref()
+            .is_some_and(|level| *level != self.level)
+        {
+            self.par_level = None;
+            self.par_search = None;
+        }
+        if let Some(level) = &self.par_level
+            && self.par_search.is_none()
+        {
+            self.par_search = Some(Search::new(&level.build(), DEFAULT_STATE_LIMIT));
+        }
+        let result = match &mut self.par_search {
+            Some(search) => match search.run(STATES_PER_FRAME) {
+                None => return format!("Searching {}", search.visited()),
+                result => result,
+            },
+            None => None,
+        };
+        match result {
+            // The par is left as it was when no solution is found
+            Some(Solution::Unsolvable) => return "Unwinnable".to_string(),
+            Some(Solution::LimitReached(_)) => return "Too hard".to_string(),
+            Some(solution) => {
+                self.level.par = solution.move_count();
+                self.par_level = None;
+                self.par_search = None;
+            }
+            None => {}
+        }
+        match self.level.par {
+            Some(par) => format!("Par {}", par),
+            None => "No par".to_string(),
+        }
+    }
+    /// Draw the buttons along the top and right side. Returns true if play was pressed.
+    fn panel(&mut self) -> bool {
+        let gray = (0x777777FF, 0x888888FF);
+        let selected = (0x3fb84aff, 0x3fb84aff);
+        // Top bar
+        let top = Bounds::new(36, 2, 34, 20);
+        let play = button("Play", top, gray.0, gray.1);
+        if button("Export", top.translate_x(38).width(40), gray.0, gray.1) {
+            self.popup = Some(Popup::Export(self.level.to_string()));
+        }
+        if button("Import", top.translate_x(82).width(40), gray.0, gray.1) {
+            self.popup = Some(Popup::Import {
+                text: String::new(),
+                error: String::new(),
+            });
+        }
+        let size_bounds = top.translate_x(128).width(16);
+        if button("-", size_bounds, gray.0, gray.1) {
+            self.resize(self.level.width - 1, self.level.height);
+        }
+        let size_text = format!("W {}", self.level.width);
+        text!(
+            size_text.as_str(),
+            x = size_bounds.x() + 20,
+            y = 8,
+            fixed = true
+        );
+        if button("+", size_bounds.translate_x(48), gray.0, gray.1) {
+            self.resize(self.level.width + 1, self.level.height);
+        }
+        let size_bounds = size_bounds.translate_x(72);
+        if button("-", size_bounds, gray.0, gray.1) {
+            self.resize(self.level.width, self.level.height - 1);
+        }
+        let size_text = format!("H {}", self.level.height);
+        text!(
+            size_text.as_str(),
+            x = size_bounds.x() + 20,
+            y = 8,
+            fixed = true
+        );
+        if button("+", size_bounds.translate_x(48), gray.0, gray.1) {
+            self.resize(self.level.width, self.level.height + 1);
+        }
+
+        // Tool options
+        let row = Bounds::new(PANEL_X, 2, 136, 16);
+        let tool_idx = Tool::ALL.iter().position(|v| *v == self.tool).unwrap_or(0);
+        if button("<", row.width(16), gray.0, gray.1) {
+            self.tool = Tool::ALL[(tool_idx + Tool::ALL.len() - 1) % Tool::ALL.len()];
+            self.linking = None;
+        }
+        text_box!(
+            self.tool.name(),
+            bounds = row.translate_y(4).adjust_width(-32).translate_x(16),
+            align = "center",
+            fixed = true,
+        );
+        if button(">", row.width(16).translate_x(120), gray.0, gray.1) {
+            self.tool = Tool::ALL[(tool_idx + 1) % Tool::ALL.len()];
+            self.linking = None;
+        }
+        let mut row = row.translate_y(18);
+        if self.tool.uses_direction() {
+            let text = format!("Direction: {:?}", self.direction);
+            if button(&text, row, gray.0, gray.1) {
+                self.direction = self.direction.rotate_right();
+            }
+            row = row.translate_y(18);
+        }
+        if self.tool == Tool::RotatingConveyor {
+            let text = format!("Other: {:?}", self.alt_direction);
+            if button(&text, row, gray.0, gray.1) {
+                self.alt_direction = self.alt_direction.rotate_right();
+            }
+            row = row.translate_y(18);
+        }
+        if self.tool.uses_flag() {
+            let text = match (self.tool, self.flag) {
+                (Tool::Door | Tool::Portal, true) => "Open",
+                (Tool::Door | Tool::Portal, false) => "Closed",
+                (Tool::Conveyor, true) => "On",
+                (Tool::Conveyor, false) => "Off",
+                (Tool::RotatingConveyor, true) => "Using other",
+                (Tool::RotatingConveyor, false) => "Using first",
+                (Tool::Mirror | Tool::RotatingMirror, true) => "Slash /",
+                (Tool::Mirror | Tool::RotatingMirror, false) => "Backslash \\",
+                (_, true) => "Short",
+                (_, false) => "Tall",
+            };
+            if button(text, row, gray.0, gray.1) {
+                self.flag = !self.flag;
+            }
+            row = row.translate_y(18);
+        }
+        if self.tool.uses_wire() {
+            let text = format!("Wire {}", self.wire_idx);
+            if button(&text, row, gray.0, gray.1) {
+                self.wire_idx = (self.wire_idx + 1) % 4;
+            }
+            row = row.translate_y(18);
+        }
+        if self.tool.uses_color() {
+            if button("Color", row, gray.0, gray.1) {
+                self.color = (self.color + 1) % PORTAL_COLORS.len();
+            }
+            rect!(
+                bounds = row.width(12).height(12).translate_x(4).translate_y(2),
+                color = PORTAL_COLORS[self.color],
+                fixed = true,
+            );
+            row = row.translate_y(18);
+        }
+        if self.tool == Tool::Gate {
+            if button(self.gate.name(), row, gray.0, gray.1) {
+                let idx = Gate::ALL.iter().position(|v| *v == self.gate).unwrap_or(0);
+                self.gate = Gate::ALL[(idx + 1) % Gate::ALL.len()];
+            }
+            row = row.translate_y(18);
+        }
+        if self.tool == Tool::Robot {
+            if button(self.roam.name(), row, gray.0, gray.1) {
+                let idx = Roam::ALL.iter().position(|v| *v == self.roam).unwrap_or(0);
+                self.roam = Roam::ALL[(idx + 1) % Roam::ALL.len()];
+            }
+            row = row.translate_y(18);
+        }
+        for (i, label) in self.tool.channel_labels(self.gate).iter().enumerate() {
+            let (name, color) = CHANNELS[self.channels[i]];
+            let text = format!("{}: {}", label, name);
+            if button(&text, row, gray.0, gray.1) {
+                self.channels[i] = (self.channels[i] + 1) % CHANNELS.len();
+            }
+            rect!(
+                bounds = row.width(12).height(12).translate_x(4).translate_y(2),
+                color = color,
+                fixed = true,
+            );
+            row = row.translate_y(18);
+        }
+
+        // Win requirements
+        let mut row = row.translate_y(6);
+        text!("Win requirements", x = row.x(), y = row.y(), fixed = true);
+        row = row.translate_y(12);
+        let mut remove = None;
+        for i in 0..self.level.requirements.len() {
+            let req = self.level.requirements[i];
+            let (name, count) = match req {
+                WinRequirement::Never => ("Never", None),
+                WinRequirement::CatsInGoals(n) => ("Cats", Some(n)),
+                WinRequirement::FiresExtinguished(n) => ("Fires", Some(n)),
+                WinRequirement::MaxMoves(n) => ("Max moves", Some(n)),
+            };
+            if button(name, row.width(64), gray.0, gray.1) {
+                self.level.requirements[i] = match req {
+                    WinRequirement::Never => WinRequirement::CatsInGoals(1),
+                    WinRequirement::CatsInGoals(n) => WinRequirement::FiresExtinguished(n),
+                    WinRequirement::FiresExtinguished(n) => WinRequirement::MaxMoves(n),
+                    WinRequirement::MaxMoves(_) => WinRequirement::Never,
+                };
+            }
+            if let Some(n) = count {
+                let change = |n: usize| match req {
+                    WinRequirement::CatsInGoals(_) => WinRequirement::CatsInGoals(n),
+                    WinRequirement::FiresExtinguished(_) => WinRequirement::FiresExtinguished(n),
+                    _ => WinRequirement::MaxMoves(n),
+                };
+                if button("-", row.width(14).translate_x(66), gray.0, gray.1) {
+                    self.level.requirements[i] = change(n.max(1) - 1);
+                }
+                let count_text = format!("{}", n);
+                text_box!(
+                    count_text.as_str(),
+                    bounds = row.width(22).translate_x(80).translate_y(4),
+                    align = "center",
+                    fixed = true,
+                );
+                if button("+", row.width(14).translate_x(102), gray.0, gray.1) {
+                    self.level.requirements[i] = change(n + 1);
+                }
+            }
+            if self.level.requirements.len() > 1
+                && button("x", row.width(16).translate_x(120), 0xBC4040FF, 0xA03030FF)
+            {
+                remove = Some(i);
+            }
+            row = row.translate_y(18);
+        }
+        if let Some(i) = remove {
+            self.level.requirements.remove(i);
+        }
+        if button("Add requirement", row, gray.0, gray.1) {
+            self.level.requirements.push(WinRequirement::MaxMoves(20));
+        }
+        row = row.translate_y(18);
+        // Par is the length of the shortest solution, if the solver can find one
+        if button("Find par", row.width(64), gray.0, gray.1) {
+            self.par_level = Some(self.level.clone());
+            self.par_search = None;
+        }
+        let par_text = self.run_par_search();
+        text!(
+            par_text.as_str(),
+            x = row.x() + 70,
+            y = row.y() + 4,
+            fixed = true
+        );
+        row = row.translate_y(18);
+        let wiring_text = if self.level.hide_wiring {
+            "Wiring hidden"
+        } else {
+            "Wiring shown"
+        };
+        if button(wiring_text, row, gray.0, gray.1) {
+            self.level.hide_wiring = !self.level.hide_wiring;
+            self.refresh();
+        }
+        row = row.translate_y(18);
+        let spread_text = match self.level.fire_spread {
+            Some(moves) => format!("Fire spreads every {}", moves),
+            None => "Fire doesn't spread".to_string(),
+        };
+        if button(&spread_text, row, gray.0, gray.1) {
+            self.level.fire_spread = match self.level.fire_spread {
+                None => Some(1),
+                Some(moves) if moves < MAX_FIRE_SPREAD => Some(moves + 1),
+                Some(_) => None,
+            };
+            self.refresh();
+        }
+        row = row.translate_y(18);
+        let cats_text = if self.level.switch_cats {
+            "One cat moves at a time"
+        } else {
+            "Every cat moves"
+        };
+        if button(cats_text, row, gray.0, gray.1) {
+            self.level.switch_cats = !self.level.switch_cats;
+            self.refresh();
+        }
+        row = row.translate_y(18);
+        let problem_text = match self.problems.first() {
+            None => "No problems found".to_string(),
+            Some(problem) if self.problems.len() == 1 => problem.to_string(),
+            Some(problem) => format!("{} ({} more)", problem, self.problems.len() - 1),
+        };
+        let problem_color = if self.problems.is_empty() {
+            0x3FB84AFF
+        } else {
+            0xBC4040FF
+        };
+        text_box!(
+            problem_text.as_str(),
+            bounds = row.height(36),
+            color = problem_color,
+            fixed = true,
+        );
+        row = row.translate_y(42);
+
+        // Caption and hint
+        for (field, name) in [(TextField::Caption, "Caption"), (TextField::Hint, "Hint")] {
+            let colors = if self.typing == Some(field) {
+                selected
+            } else {
+                gray
+            };
+            if button(name, row, colors.0, colors.1) {
+                self.typing = if self.typing == Some(field) {
+                    None
+                } else {
+                    Some(field)
+                };
+            }
+            row = row.translate_y(18);
+        }
+        if self.typing == Some(TextField::Hint) {
+            text_box!(
+                self.level.hint.as_str(),
+                bounds = row.height(60),
+                color = 0xCBB41CFF,
+                fixed = true,
+            );
+        }
+        play
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{
+    levels::WinState,
+    object::ObjectInfo,
+    util::{Direction, Point},
+    world::{Edit, Sound, World, WorldEvent},
+};
+
+impl World {
+    /// Returns true if fire spreads at the end of the next move
+    pub fn fire_spreads_next_move(&self) -> bool {
+        match self.fire_spread {
+            Some(moves) => (self.move_id + 1).is_multiple_of(moves),
+            None => false,
+        }
+    }
+    /// Cells that fire would spread to if it spread now.
+    /// Fire spreads to neighboring boxes and goals, unless they are soaked by water or walled off.
+    pub fn next_fires(&self) -> Vec<Point> {
+        let mut out = vec![];
+        for point in self.cells_iterator() {
+            if !self[point].iter().any(|v| v.obj_type == ObjectInfo::Fire) {
+                continue;
+            }
+            for dir in Direction::iter_all() {
+                let next = point + dir;
+                if self.point_inside(next)
+                    && !self.edge_blocked(point, dir)
+                    && !out.contains(&next)
+                    && self[next].iter().any(|v| is_flammable(&v.obj_type))
+                    && !self.soaked(next)
+                {
+                    out.push(next);
+                }
+            }
+        }
+        out
+    }
+    /// Returns true if there is water on the point or next to it, which keeps it from catching fire
+    fn soaked(&self, point: Point) -> bool {
+        std::iter::once(point)
+            .chain(Direction::iter_all().map(|dir| point + dir))
+            .any(|p| {
+                self.point_inside(p) && self[p].iter().any(|v| v.obj_type == ObjectInfo::Water)
+            })
+    }
+    /// Spread fire one cell if the level's fire spread rule says it is time.
+    /// Called at the end of each move.
+    pub fn spread_fire(&mut self) {
+        if !self.fire_spreads_next_move() {
+            return;
+        }
+        for point in self.next_fires() {
+            for i in 0..self[point].len() {
+                if is_flammable(&self[point][i].obj_type) {
+                    self.ignite(point, i);
+                }
+            }
+            if self[point].iter().any(|v| v.obj_type == ObjectInfo::Cat) {
+                self.die(WinState::Burnt);
+            }
+        }
+    }
+    /// Burn up the object at index i of the cell and set the cell on fire
+    pub fn ignite(&mut self, point: Point, i: usize) {
+        let old = std::mem::replace(&mut self[point][i].obj_type, ObjectInfo::BurntBox);
+        self.edit_history.push((
+            self.move_id,
+            Edit::ChangeObjInfo(point, self[point][i].id, old),
+        ));
+        self.set_animation(point, i, 10, 30);
+        if !self[point].iter().any(|v| v.obj_type == ObjectInfo::Fire) {
+            let id = self.summon_object(point, ObjectInfo::Fire);
+            self.edit_history
+                .push((self.move_id, Edit::SummonObject(point, id)));
+        }
+        self.events.push(WorldEvent::Sound(Sound::Fire));
+    }
+}
+
+/// Returns true if spreading fire burns the object
+fn is_flammable(obj: &ObjectInfo) -> bool {
+    matches!(obj, ObjectInfo::Box | ObjectInfo::Goal)
+}
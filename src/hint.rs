@@ -0,0 +1,164 @@
+use crate::{
+    levels::WinState,
+    object::{ObjectId, ObjectInfo},
+    solver::{Action, DEFAULT_STATE_LIMIT, STATES_PER_FRAME, Search, Solution},
+    util::Direction,
+    world::World,
+};
+use turbo::*;
+
+/// What the player should do next
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
+pub enum Hint {
+    /// Move in this direction to stay on the shortest solution
+    Move(Direction),
+    /// Switch to the next cat to stay on the shortest solution
+    SwitchCat,
+    /// The level can't be won from here, but can be after this many undos
+    Undo(usize),
+    /// The level can't be won at all, even from the start
+    Unwinnable,
+    /// The search gave up before finding an answer
+    TooHard,
+    /// Every win requirement is already met, so there is nothing left to do
+    AlreadySolved,
+}
+
+/// A hint being searched for from the world the player is looking at
+#[turbo::serialize]
+pub struct HintSearch {
+    /// (move_id, number of edits, active cat) of the world the hint is for,
+    /// to notice when the player moves or switches cats
+    world_id: (usize, usize, Option<ObjectId>),
+    /// Number of undos applied to the world being searched
+    undos: usize,
+    /// Search from the world with the undos applied, started by `run`.
+    /// Left out of the saved state, so it starts over after a hot reload.
+    #[borsh(skip)]
+    #[serde(skip)]
+    search: Option<Search>,
+    result: Option<Hint>,
+}
+
+impl HintSearch {
+    pub fn new(world: &World) -> Self {
+        // A dead world can't be won without undoing at least once
+        let undos = if world.win_state == WinState::Alive {
+            0
+        } else {
+            1
+        };
+        Self {
+            world_id: world_id(world),
+            undos,
+            search: None,
+            result: None,
+        }
+    }
+    /// Returns true if the hint was searched for from this state of the world
+    pub fn is_for(&self, world: &World) -> bool {
+        self.world_id == world_id(world)
+    }
+    /// Search a little further, returning the hint once it is found
+    pub fn run(&mut self, world: &World) -> Option<Hint> {
+        if self.result.is_some() {
+            return self.result;
+        }
+        let search = self
+            .search
+            .get_or_insert_with(|| Search::new(&undone(world, self.undos), DEFAULT_STATE_LIMIT));
+        self.result = match search.run(STATES_PER_FRAME)? {
+            Solution::Solved(actions) if self.undos == 0 => match actions.first() {
+                Some(Action::Move(dir)) => Some(Hint::Move(*dir)),
+                Some(Action::SwitchCat) => Some(Hint::SwitchCat),
+                None => Some(Hint::AlreadySolved),
+            },
+            Solution::Solved(_) => Some(Hint::Undo(self.undos)),
+            Solution::LimitReached(_) => Some(Hint::TooHard),
+            Solution::Unsolvable if self.undos >= world.move_id => Some(Hint::Unwinnable),
+            Solution::Unsolvable => {
+                self.undos += 1;
+                self.search = None;
+                None
+            }
+        };
+        self.result
+    }
+    /// Draw the hint, or how far the search has got
+    pub fn draw(&self, world: &World, bounds: Bounds) {
+        let text = match self.result {
+            None => format!(
+                "Thinking... {}",
+                self.search.as_ref().map_or(0, |search| search.visited())
+            ),
+            Some(Hint::Move(dir)) => format!("Next move: {}", direction_name(dir)),
+            Some(Hint::SwitchCat) => "Next move: Switch cat".to_string(),
+            Some(Hint::Undo(1)) => "Unwinnable, undo 1 move".to_string(),
+            Some(Hint::Undo(undos)) => format!("Unwinnable, undo {} moves", undos),
+            Some(Hint::Unwinnable) => "This level can't be won".to_string(),
+            Some(Hint::TooHard) => "Too many possibilities".to_string(),
+            Some(Hint::AlreadySolved) => "Already solved".to_string(),
+        };
+        text_box!(
+            text.as_str(),
+            bounds = bounds.left_of_self().adjust_width(150).translate_x(-155),
+            align = "right",
+            fixed = true,
+        );
+        let Some(Hint::Move(dir)) = self.result else {
+            return;
+        };
+        // Point an arrow from every cat that moves towards where it should go
+        let (dx, dy) = match dir {
+            Direction::North => (-7, -14),
+            Direction::South => (7, 14),
+            Direction::East => (19, 0),
+            Direction::West => (-19, 0),
+        };
+        let active = world.active_cat();
+        for point in world.cells_iterator() {
+            if world[point]
+                .iter()
+                .any(|v| v.obj_type == ObjectInfo::Cat && active.is_none_or(|id| id == v.id))
+            {
+                let pos = World::to_screen_space(point);
+                let center = (pos.0 + 26, pos.1 + 4);
+                path!(
+                    start = center,
+                    end = (center.0 + dx, center.1 + dy),
+                    size = 3,
+                    color = 0xCBB41CFF
+                );
+                circ!(
+                    d = 7,
+                    x = center.0 + dx - 3,
+                    y = center.1 + dy - 3,
+                    color = 0xCBB41CFF
+                );
+            }
+        }
+    }
+}
+
+fn direction_name(dir: Direction) -> &'static str {
+    match dir {
+        Direction::North => "Up",
+        Direction::South => "Down",
+        Direction::East => "Right",
+        Direction::West => "Left",
+    }
+}
+
+fn world_id(world: &World) -> (usize, usize, Option<ObjectId>) {
+    (world.move_id, world.edit_history.len(), world.active_cat())
+}
+
+/// Copy of the world with some moves undone
+fn undone(world: &World, undos: usize) -> World {
+    let mut out = world.clone();
+    for _ in 0..undos {
+        out.undo();
+    }
+    out
+}
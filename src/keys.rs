@@ -0,0 +1,96 @@
+use crate::{
+    object::{MoveType, Object, ObjectId, ObjectInfo},
+    util::{Direction, Point},
+    world::{Edit, Sound, World, WorldEvent},
+};
+
+impl World {
+    /// Colors of the keys a cat is carrying
+    pub fn keys_held(&self, cat: ObjectId) -> impl Iterator<Item = u32> + '_ {
+        self.keys
+            .iter()
+            .filter(move |(holder, _)| *holder == cat)
+            .map(|(_, color)| *color)
+    }
+    /// Give a cat a key and log it in history
+    pub fn give_key(&mut self, cat: ObjectId, color: u32) {
+        self.store_key(cat, color, true);
+        self.edit_history
+            .push((self.move_id, Edit::GiveKey(cat, color)));
+    }
+    /// Take a key from a cat and log it in history, returning false if the cat doesn't have one
+    pub fn take_key(&mut self, cat: ObjectId, color: u32) -> bool {
+        if !self.store_key(cat, color, false) {
+            return false;
+        }
+        self.edit_history
+            .push((self.move_id, Edit::TakeKey(cat, color)));
+        true
+    }
+    /// Add or remove a key without logging it in history, returning true if anything changed.
+    /// Keys are kept sorted so undoing and redoing leaves them in the same order.
+    pub fn store_key(&mut self, cat: ObjectId, color: u32, held: bool) -> bool {
+        let key = (cat, color);
+        match (self.keys.binary_search(&key), held) {
+            (Ok(idx), false) => {
+                self.keys.remove(idx);
+                true
+            }
+            (Err(idx), true) | (Ok(idx), true) => {
+                self.keys.insert(idx, key);
+                true
+            }
+            (Err(_), false) => false,
+        }
+    }
+    /// Let the first cat in the cell pick up every key there
+    pub fn pick_up_keys(&mut self, point: Point) {
+        let Some(cat) = self[point].iter().find(|v| v.obj_type == ObjectInfo::Cat) else {
+            return;
+        };
+        let cat = cat.id;
+        let mut i = 0;
+        while i < self[point].len() {
+            if let ObjectInfo::Key(color) = self[point][i].obj_type {
+                let key = self[point].remove(i);
+                self.edit_history.push((
+                    self.move_id,
+                    Edit::DeleteObject(point, i, key.id, key.obj_type),
+                ));
+                self.give_key(cat, color);
+            } else {
+                i += 1;
+            }
+        }
+    }
+    /// How an object reacts to being pushed, treating locked doors as open for cats with a matching key
+    pub fn push_ability(&self, item: &Object, pusher: &Object, dir: Direction) -> MoveType {
+        match item.obj_type {
+            ObjectInfo::LockedDoor(_, color)
+                if pusher.obj_type == ObjectInfo::Cat
+                    && self.keys.binary_search(&(pusher.id, color)).is_ok() =>
+            {
+                MoveType::MoveOver
+            }
+            _ => item.test_push_by(&pusher.obj_type, dir),
+        }
+    }
+    /// Open the locked doors at the point that the cat has keys for, using up one key for each
+    pub fn unlock(&mut self, point: Point, cat: ObjectId) {
+        let mut i = 0;
+        while i < self[point].len() {
+            if let ObjectInfo::LockedDoor(_, color) = self[point][i].obj_type
+                && self.take_key(cat, color)
+            {
+                let door = self[point].remove(i);
+                self.edit_history.push((
+                    self.move_id,
+                    Edit::DeleteObject(point, i, door.id, door.obj_type),
+                ));
+                self.events.push(WorldEvent::Sound(Sound::Door));
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
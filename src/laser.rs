@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use crate::{
+    levels::WinState,
+    object::ObjectInfo,
+    util::{Direction, Point},
+    world::World,
+};
+
+/// Most times the beams are traced in one move while receivers open and close the doors in their way
+const MAX_LASER_PASSES: usize = 8;
+
+/// Path of one laser beam
+pub struct Beam {
+    /// Cell of the emitter
+    pub start: Point,
+    /// Cells the beam enters, in order. The last one is where it stopped, unless it left the level.
+    pub cells: Vec<Point>,
+}
+
+impl World {
+    /// Trace the beam of every laser emitter
+    pub fn beams(&self) -> Vec<Beam> {
+        let mut beams = vec![];
+        for point in self.cells_iterator() {
+            for obj in self[point].iter() {
+                if let ObjectInfo::Laser(dir) = obj.obj_type {
+                    beams.push(Beam {
+                        start: point,
+                        cells: self.trace_beam(point, dir),
+                    });
+                }
+            }
+        }
+        beams
+    }
+    /// Cells a beam leaving the start cell in the direction dir passes through, bouncing off mirrors
+    fn trace_beam(&self, start: Point, mut dir: Direction) -> Vec<Point> {
+        let mut cells = vec![];
+        let mut seen = HashSet::new();
+        if self.edge_blocked(start, dir) {
+            return cells;
+        }
+        let mut point = start + dir;
+        // Mirrors can bounce a beam around in a loop, so stop once it goes somewhere it has been
+        while self.point_inside(point) && seen.insert((point, dir)) {
+            cells.push(point);
+            let mut stopped = false;
+            for obj in self[point].iter() {
+                match obj.obj_type {
+                    ObjectInfo::Mirror(slash) | ObjectInfo::RotateableMirror(slash, false) => {
+                        dir = deflect(dir, slash);
+                    }
+                    ObjectInfo::RotateableMirror(slash, true) => dir = deflect(dir, !slash),
+                    ObjectInfo::Barrier
+                    | ObjectInfo::Box
+                    | ObjectInfo::Cat
+                    | ObjectInfo::Water
+                    | ObjectInfo::Door(_, false)
+                    | ObjectInfo::LockedDoor(..)
+                    | ObjectInfo::Robot(..)
+                    | ObjectInfo::Laser(_)
+                    | ObjectInfo::LaserReceiver(..) => stopped = true,
+                    _ => {}
+                }
+            }
+            if stopped || self.edge_blocked(point, dir) {
+                break;
+            }
+            point += dir;
+        }
+        cells
+    }
+    /// Power receivers that beams reach, then burn the boxes and cats at the ends of the beams.
+    /// Called after everything has moved.
+    pub fn fire_lasers(&mut self) {
+        for _ in 0..MAX_LASER_PASSES {
+            let lit: HashSet<Point> = self
+                .beams()
+                .into_iter()
+                .filter_map(|beam| beam.cells.last().copied())
+                .collect();
+            let mut changed = false;
+            for point in self.cells_iterator() {
+                for i in 0..self[point].len() {
+                    if let ObjectInfo::LaserReceiver(target, idx) = self[point][i].obj_type {
+                        let on = lit.contains(&point);
+                        if self.set_wiring(target, idx, on) {
+                            self.set_animation(point, i, on as i32, 1);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        for beam in self.beams() {
+            let Some(&end) = beam.cells.last() else {
+                continue;
+            };
+            for i in 0..self[end].len() {
+                match self[end][i].obj_type {
+                    ObjectInfo::Cat => {
+                        self.die(WinState::Lasered);
+                        self.set_animation(end, i, 8, 20);
+                    }
+                    ObjectInfo::Box => self.ignite(end, i),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Direction a beam leaves a mirror in, where slash is true for a / mirror and false for a \ mirror
+fn deflect(dir: Direction, slash: bool) -> Direction {
+    if slash {
+        dir.deflect_slash()
+    } else {
+        dir.deflect_backslash()
+    }
+}
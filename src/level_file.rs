@@ -0,0 +1,810 @@
+//! Plain text level format.
+//!
+//! ```text
+//! ; Lines starting with a semicolon are comments
+//! size 7 4
+//! floors
+//! .#.....
+//! .#.....
+//! .#.....
+//! .......
+//! win CatsInGoals 1
+//! caption Buttons can open doors.\nSquare buttons toggle on and off.
+//! hint Try the square button first
+//! par 12
+//! hide_wiring
+//! fire_spread 3
+//! switch_cats
+//! object 0 0 Goal
+//! object 0 1 Door East closed
+//! object 3 0 ToggleButton 0 1 0 anim 1
+//! object 4 0 ToggleSwitch red
+//! object 5 0 Gate Not blue 1 red
+//! object 6 1 Relay blue 0
+//! object 6 2 Laser West
+//! object 4 2 Mirror /
+//! wire 0 1 0
+//! channel red
+//! ```
+//!
+//! Floors are `.` and missing floors are `#`; walls are generated from the floors the same way
+//! [`LevelBuilder::make_level`] does. Each `object` line summons one object on top of the
+//! previous ones in that cell, each `wire` line turns on one of the four wires of a cell,
+//! and each `channel` line turns on a named channel. `hide_wiring` stops the wiring overlay
+//! from giving away which button powers what, and `fire_spread N` makes fire spread to
+//! neighboring boxes and goals every N moves. `switch_cats` makes only one cat move at a time,
+//! with the player switching between them.
+//! Gates are written as the gate, the output channel, the number of inputs, then the inputs.
+//! Portals give the number of ends before the ends.
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    levels::{LevelBuilder, WinRequirement},
+    object::ObjectInfo,
+    robot::Roam,
+    signal::Gate,
+    util::{Direction, Point},
+    world::World,
+};
+
+/// Error produced when a level file is malformed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// One-indexed line of the error
+    pub line: usize,
+    /// One-indexed column of the error
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_fmt(format_args!(
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        ))
+    }
+}
+
+/// Everything needed to build a level, as read from or written to a level file
+#[turbo::serialize]
+#[derive(PartialEq)]
+pub struct LevelFile {
+    pub width: usize,
+    pub height: usize,
+    /// Rows of cells, true where there is a floor
+    pub floors: Vec<Vec<bool>>,
+    pub requirements: Vec<WinRequirement>,
+    pub caption: String,
+    pub hint: String,
+    /// Fewest moves the level can be won in
+    pub par: Option<usize>,
+    /// Hide the wiring overlay while playing
+    pub hide_wiring: bool,
+    /// Fire spreads every this many moves
+    pub fire_spread: Option<usize>,
+    /// Only the active cat moves
+    pub switch_cats: bool,
+    /// List of (location, object, starting animation)
+    pub objects: Vec<(Point, ObjectInfo, i32)>,
+    /// List of (location, wire index) that start active
+    pub wiring: Vec<(Point, usize)>,
+    /// Channels that start on
+    pub channels: Vec<String>,
+}
+
+impl LevelFile {
+    /// Read a level file
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut size: Option<(usize, usize)> = None;
+        let mut out = LevelFile {
+            width: 0,
+            height: 0,
+            floors: vec![],
+            requirements: vec![],
+            caption: String::new(),
+            hint: String::new(),
+            par: None,
+            hide_wiring: false,
+            fire_spread: None,
+            switch_cats: false,
+            objects: vec![],
+            wiring: vec![],
+            channels: vec![],
+        };
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let mut last_line = 0;
+        while let Some((line_num, line)) = lines.next() {
+            last_line = line_num;
+            let mut cursor = Cursor::new(line_num, line);
+            let Some((column, keyword)) = cursor.next() else {
+                continue;
+            };
+            if keyword.starts_with(';') {
+                continue;
+            }
+            match keyword {
+                "size" => {
+                    if size.is_some() {
+                        return Err(cursor.error_at(column, "size is given twice"));
+                    }
+                    let width = cursor.number::<usize>("width")?;
+                    let height = cursor.number::<usize>("height")?;
+                    if width == 0 || height == 0 {
+                        return Err(cursor.error_at(column, "level must be at least 1x1"));
+                    }
+                    cursor.end()?;
+                    size = Some((width, height));
+                    out.width = width;
+                    out.height = height;
+                }
+                "floors" => {
+                    let Some((width, height)) = size else {
+                        return Err(cursor.error_at(column, "floors must come after size"));
+                    };
+                    if !out.floors.is_empty() {
+                        return Err(cursor.error_at(column, "floors are given twice"));
+                    }
+                    cursor.end()?;
+                    for _ in 0..height {
+                        let Some((row_num, row)) = lines.next() else {
+                            return Err(ParseError {
+                                line: line_num,
+                                column,
+                                message: format!("expected {} rows of floors", height),
+                            });
+                        };
+                        last_line = row_num;
+                        let row = row.trim_end();
+                        let mut floor_row = vec![];
+                        for (i, c) in row.chars().enumerate() {
+                            match c {
+                                '.' => floor_row.push(true),
+                                '#' => floor_row.push(false),
+                                _ => {
+                                    return Err(ParseError {
+                                        line: row_num,
+                                        column: i + 1,
+                                        message: format!("expected '.' or '#', found '{}'", c),
+                                    });
+                                }
+                            }
+                        }
+                        if floor_row.len() != width {
+                            return Err(ParseError {
+                                line: row_num,
+                                column: floor_row.len().min(width) + 1,
+                                message: format!(
+                                    "expected {} floors, found {}",
+                                    width,
+                                    floor_row.len()
+                                ),
+                            });
+                        }
+                        out.floors.push(floor_row);
+                    }
+                }
+                "win" => {
+                    let (column, name) = cursor.expect("a win requirement")?;
+                    let req = match name {
+                        "Never" => WinRequirement::Never,
+                        "CatsInGoals" => WinRequirement::CatsInGoals(cursor.number("cat count")?),
+                        "FiresExtinguished" => {
+                            WinRequirement::FiresExtinguished(cursor.number("fire count")?)
+                        }
+                        "MaxMoves" => WinRequirement::MaxMoves(cursor.number("move count")?),
+                        _ => {
+                            return Err(cursor
+                                .error_at(column, &format!("unknown win requirement '{}'", name)));
+                        }
+                    };
+                    cursor.end()?;
+                    out.requirements.push(req);
+                }
+                "caption" => out.caption = unescape(cursor.rest()),
+                "hint" => out.hint = unescape(cursor.rest()),
+                "par" => {
+                    out.par = Some(cursor.number("par")?);
+                    cursor.end()?;
+                }
+                "hide_wiring" => {
+                    cursor.end()?;
+                    out.hide_wiring = true;
+                }
+                "fire_spread" => {
+                    let column = cursor.next_column();
+                    let moves = cursor.number("fire spread")?;
+                    if moves == 0 {
+                        return Err(cursor.error_at(column, "fire spread must be at least 1"));
+                    }
+                    cursor.end()?;
+                    out.fire_spread = Some(moves);
+                }
+                "switch_cats" => {
+                    cursor.end()?;
+                    out.switch_cats = true;
+                }
+                "object" => {
+                    let point = cursor.point(size)?;
+                    let obj = cursor.object(size)?;
+                    let mut anim = 0;
+                    if let Some((column, word)) = cursor.next() {
+                        if word != "anim" {
+                            return Err(cursor.error_at(
+                                column,
+                                &format!("expected 'anim' or end of line, found '{}'", word),
+                            ));
+                        }
+                        anim = cursor.number("animation")?;
+                    }
+                    cursor.end()?;
+                    out.objects.push((point, obj, anim));
+                }
+                "wire" => {
+                    let point = cursor.point(size)?;
+                    let idx = cursor.wire_index()?;
+                    cursor.end()?;
+                    out.wiring.push((point, idx));
+                }
+                "channel" => {
+                    let name = cursor.channel()?;
+                    cursor.end()?;
+                    out.channels.push(name);
+                }
+                _ => {
+                    return Err(cursor.error_at(column, &format!("unknown keyword '{}'", keyword)));
+                }
+            }
+        }
+        let end_error = |message: &str| ParseError {
+            line: last_line.max(1),
+            column: 1,
+            message: message.to_string(),
+        };
+        if size.is_none() {
+            return Err(end_error("missing size"));
+        }
+        if out.floors.is_empty() {
+            return Err(end_error("missing floors"));
+        }
+        if out.requirements.is_empty() {
+            return Err(end_error("missing win requirement"));
+        }
+        Ok(out)
+    }
+    /// Read the layout of an existing world.
+    /// Walls that would be generated from the floors are left out.
+    pub fn from_world(world: &World) -> Self {
+        let floors: Vec<Vec<bool>> = (0..world.height)
+            .map(|y| {
+                (0..world.width)
+                    .map(|x| {
+                        !world[(x, y).into()]
+                            .iter()
+                            .any(|v| v.obj_type == ObjectInfo::Barrier)
+                    })
+                    .collect()
+            })
+            .collect();
+        let rows: Vec<&[bool]> = floors.iter().map(|row| row.as_slice()).collect();
+        let template =
+            LevelBuilder::make_level(world.width, world.height, &rows, WinRequirement::Never)
+                .finish();
+        let mut objects = vec![];
+        let mut wiring = vec![];
+        for point in world.cells_iterator() {
+            let generated = template[point]
+                .iter()
+                .zip(world[point].iter())
+                .take_while(|(a, b)| a.obj_type == b.obj_type)
+                .count();
+            for obj in world[point].iter().skip(generated) {
+                objects.push((point, obj.obj_type.clone(), obj.animation.end));
+            }
+            let wires = world.wiring[point.x() as usize + point.y() as usize * world.width];
+            for (idx, active) in wires.iter().enumerate() {
+                if *active {
+                    wiring.push((point, idx));
+                }
+            }
+        }
+        LevelFile {
+            width: world.width,
+            height: world.height,
+            floors,
+            requirements: world.requirements.clone(),
+            caption: world.caption.clone(),
+            hint: world.hint.clone(),
+            par: world.par,
+            hide_wiring: world.hide_wiring,
+            fire_spread: world.fire_spread,
+            switch_cats: world.switch_cats,
+            objects,
+            wiring,
+            channels: world.channels.iter().cloned().collect(),
+        }
+    }
+    /// Build a playable world from the level
+    pub fn build(&self) -> World {
+        let rows: Vec<&[bool]> = self.floors.iter().map(|row| row.as_slice()).collect();
+        let mut builder = LevelBuilder::make_level(
+            self.width,
+            self.height,
+            &rows,
+            self.requirements
+                .first()
+                .copied()
+                .unwrap_or(WinRequirement::Never),
+        );
+        for req in self.requirements.iter().skip(1) {
+            builder = builder.with_win_req(*req);
+        }
+        for (point, obj, anim) in self.objects.iter() {
+            builder = if *anim == 0 {
+                builder.with_obj((*point).into(), obj.clone())
+            } else {
+                builder.with_obj_anim((*point).into(), obj.clone(), *anim)
+            };
+        }
+        for (point, idx) in self.wiring.iter() {
+            builder = builder.with_wiring((*point).into(), *idx, true);
+        }
+        for name in self.channels.iter() {
+            builder = builder.with_channel(name, true);
+        }
+        if let Some(par) = self.par {
+            builder = builder.with_par(par);
+        }
+        if self.hide_wiring {
+            builder = builder.with_hidden_wiring();
+        }
+        if let Some(moves) = self.fire_spread {
+            builder = builder.with_fire_spread(moves);
+        }
+        if self.switch_cats {
+            builder = builder.with_switching_cats();
+        }
+        builder
+            .with_caption(&self.caption)
+            .with_hint(&self.hint)
+            .finish()
+    }
+}
+
+impl Display for LevelFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        writeln!(f, "size {} {}", self.width, self.height)?;
+        writeln!(f, "floors")?;
+        for row in self.floors.iter() {
+            let row: String = row.iter().map(|v| if *v { '.' } else { '#' }).collect();
+            writeln!(f, "{}", row)?;
+        }
+        for req in self.requirements.iter() {
+            match req {
+                WinRequirement::Never => writeln!(f, "win Never")?,
+                WinRequirement::CatsInGoals(n) => writeln!(f, "win CatsInGoals {}", n)?,
+                WinRequirement::FiresExtinguished(n) => writeln!(f, "win FiresExtinguished {}", n)?,
+                WinRequirement::MaxMoves(n) => writeln!(f, "win MaxMoves {}", n)?,
+            }
+        }
+        if !self.caption.is_empty() {
+            writeln!(f, "caption {}", escape(&self.caption))?;
+        }
+        if !self.hint.is_empty() {
+            writeln!(f, "hint {}", escape(&self.hint))?;
+        }
+        if let Some(par) = self.par {
+            writeln!(f, "par {}", par)?;
+        }
+        if self.hide_wiring {
+            writeln!(f, "hide_wiring")?;
+        }
+        if let Some(moves) = self.fire_spread {
+            writeln!(f, "fire_spread {}", moves)?;
+        }
+        if self.switch_cats {
+            writeln!(f, "switch_cats")?;
+        }
+        for (point, obj, anim) in self.objects.iter() {
+            write!(
+                f,
+                "object {} {} {}",
+                point.x(),
+                point.y(),
+                object_string(obj)
+            )?;
+            if *anim != 0 {
+                write!(f, " anim {}", anim)?;
+            }
+            writeln!(f)?;
+        }
+        for (point, idx) in self.wiring.iter() {
+            writeln!(f, "wire {} {} {}", point.x(), point.y(), idx)?;
+        }
+        for name in self.channels.iter() {
+            writeln!(f, "channel {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a level file into a playable world
+pub fn parse_level(text: &str) -> Result<World, ParseError> {
+    LevelFile::parse(text).map(|level| level.build())
+}
+
+/// Write a world as a level file
+pub fn serialize_level(world: &World) -> String {
+    LevelFile::from_world(world).to_string()
+}
+
+fn direction_string(dir: Direction) -> &'static str {
+    match dir {
+        Direction::North => "North",
+        Direction::South => "South",
+        Direction::East => "East",
+        Direction::West => "West",
+    }
+}
+
+fn flag_string(value: bool, on: &'static str, off: &'static str) -> &'static str {
+    if value { on } else { off }
+}
+
+/// Text form of an object, as it appears after the location in an object line
+fn object_string(obj: &ObjectInfo) -> String {
+    match obj {
+        ObjectInfo::Cat => "Cat".to_string(),
+        ObjectInfo::Goal => "Goal".to_string(),
+        ObjectInfo::Box => "Box".to_string(),
+        ObjectInfo::Barrier => "Barrier".to_string(),
+        ObjectInfo::WallLeft(short) => format!("WallLeft {}", flag_string(*short, "short", "tall")),
+        ObjectInfo::WallRight(short) => {
+            format!("WallRight {}", flag_string(*short, "short", "tall"))
+        }
+        ObjectInfo::WallBack(short) => format!("WallBack {}", flag_string(*short, "short", "tall")),
+        ObjectInfo::WallFront => "WallFront".to_string(),
+        ObjectInfo::PushButton(dst, idx) => format!("PushButton {} {} {}", dst.x(), dst.y(), idx),
+        ObjectInfo::ToggleButton(dst, idx) => {
+            format!("ToggleButton {} {} {}", dst.x(), dst.y(), idx)
+        }
+        ObjectInfo::Door(dir, open) => format!(
+            "Door {} {}",
+            direction_string(*dir),
+            flag_string(*open, "open", "closed")
+        ),
+        ObjectInfo::Trap => "Trap".to_string(),
+        ObjectInfo::Death => "Death".to_string(),
+        ObjectInfo::ToggleableConveyor(dir, on) => format!(
+            "ToggleableConveyor {} {}",
+            direction_string(*dir),
+            flag_string(*on, "on", "off")
+        ),
+        ObjectInfo::RotateableConveyor(dir1, dir2, on) => format!(
+            "RotateableConveyor {} {} {}",
+            direction_string(*dir1),
+            direction_string(*dir2),
+            flag_string(*on, "on", "off")
+        ),
+        ObjectInfo::BurntBox => "BurntBox".to_string(),
+        ObjectInfo::Fire => "Fire".to_string(),
+        ObjectInfo::FireOut => "FireOut".to_string(),
+        ObjectInfo::Water => "Water".to_string(),
+        ObjectInfo::Portal(ends, open, color) => {
+            let mut out = format!(
+                "Portal {} {:08X} {}",
+                flag_string(*open, "open", "closed"),
+                color,
+                ends.len()
+            );
+            for end in ends.iter() {
+                out.push_str(&format!(" {} {}", end.x(), end.y()));
+            }
+            out
+        }
+        ObjectInfo::PushSwitch(channel) => format!("PushSwitch {}", channel),
+        ObjectInfo::ToggleSwitch(channel) => format!("ToggleSwitch {}", channel),
+        ObjectInfo::Gate(gate, inputs, output) => {
+            let mut out = format!("Gate {} {} {}", gate.name(), output, inputs.len());
+            for input in inputs.iter() {
+                out.push_str(&format!(" {}", input));
+            }
+            out
+        }
+        ObjectInfo::Delay(input, output, last, on) => format!(
+            "Delay {} {} {} {}",
+            input,
+            output,
+            flag_string(*last, "on", "off"),
+            flag_string(*on, "on", "off")
+        ),
+        ObjectInfo::Latch(set, reset, output, on) => format!(
+            "Latch {} {} {} {}",
+            set,
+            reset,
+            output,
+            flag_string(*on, "on", "off")
+        ),
+        ObjectInfo::Relay(channel, idx) => format!("Relay {} {}", channel, idx),
+        ObjectInfo::Laser(dir) => format!("Laser {}", direction_string(*dir)),
+        ObjectInfo::Mirror(slash) => format!("Mirror {}", flag_string(*slash, "/", "\\")),
+        ObjectInfo::RotateableMirror(slash, on) => format!(
+            "RotateableMirror {} {}",
+            flag_string(*slash, "/", "\\"),
+            flag_string(*on, "on", "off")
+        ),
+        ObjectInfo::LaserReceiver(dst, idx) => {
+            format!("LaserReceiver {} {} {}", dst.x(), dst.y(), idx)
+        }
+        ObjectInfo::Ice => "Ice".to_string(),
+        ObjectInfo::Key(color) => format!("Key {:08X}", color),
+        ObjectInfo::OneWay(dir) => format!("OneWay {}", direction_string(*dir)),
+        ObjectInfo::Robot(roam, dir) => {
+            format!("Robot {} {}", roam.name(), direction_string(*dir))
+        }
+        ObjectInfo::Pit(filled) => format!("Pit {}", flag_string(*filled, "filled", "open")),
+        ObjectInfo::LockedDoor(dir, color) => {
+            format!("LockedDoor {} {:08X}", direction_string(*dir), color)
+        }
+    }
+}
+
+/// Escape newlines and backslashes so text fits on one line
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Walks over the whitespace separated words of one line, keeping track of columns for errors
+struct Cursor<'a> {
+    line_num: usize,
+    line: &'a str,
+    /// Byte offset of the next unread character
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line_num: usize, line: &'a str) -> Self {
+        Cursor {
+            line_num,
+            line,
+            offset: 0,
+        }
+    }
+    fn column_of(&self, offset: usize) -> usize {
+        self.line[..offset].chars().count() + 1
+    }
+    fn error_at(&self, column: usize, message: &str) -> ParseError {
+        ParseError {
+            line: self.line_num,
+            column,
+            message: message.to_string(),
+        }
+    }
+    /// Column of the next word, or one past the end of the line if there are no more words
+    fn next_column(&self) -> usize {
+        let rest = &self.line[self.offset..];
+        self.column_of(self.offset + (rest.len() - rest.trim_start().len()))
+    }
+    /// Returns the next word and its column
+    fn next(&mut self) -> Option<(usize, &'a str)> {
+        let rest = &self.line[self.offset..];
+        let start = self.offset + (rest.len() - rest.trim_start().len());
+        let word_len = self.line[start..]
+            .find(char::is_whitespace)
+            .unwrap_or(self.line.len() - start);
+        if word_len == 0 {
+            self.offset = self.line.len();
+            return None;
+        }
+        self.offset = start + word_len;
+        Some((self.column_of(start), &self.line[start..self.offset]))
+    }
+    /// Returns the rest of the line after a single separating space
+    fn rest(&mut self) -> &'a str {
+        let rest = &self.line[self.offset..];
+        self.offset = self.line.len();
+        rest.strip_prefix(' ').unwrap_or(rest)
+    }
+    fn expect(&mut self, what: &str) -> Result<(usize, &'a str), ParseError> {
+        let column = self.next_column();
+        self.next()
+            .ok_or_else(|| self.error_at(column, &format!("expected {}", what)))
+    }
+    fn end(&mut self) -> Result<(), ParseError> {
+        match self.next() {
+            Some((column, word)) => {
+                Err(self.error_at(column, &format!("expected end of line, found '{}'", word)))
+            }
+            None => Ok(()),
+        }
+    }
+    fn number<T: std::str::FromStr>(&mut self, what: &str) -> Result<T, ParseError> {
+        let (column, word) = self.expect(what)?;
+        word.parse().map_err(|_| {
+            self.error_at(
+                column,
+                &format!("expected {} to be a number, found '{}'", what, word),
+            )
+        })
+    }
+    /// Reads an x and y coordinate that must be inside a level of the given size
+    fn point(&mut self, size: Option<(usize, usize)>) -> Result<Point, ParseError> {
+        let column = self.next_column();
+        let Some((width, height)) = size else {
+            return Err(self.error_at(1, "size must come before any locations"));
+        };
+        let x = self.number::<i32>("x coordinate")?;
+        let y = self.number::<i32>("y coordinate")?;
+        if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+            return Err(self.error_at(
+                column,
+                &format!("location ({}, {}) is outside the level", x, y),
+            ));
+        }
+        Ok(Point::from((x, y)))
+    }
+    fn wire_index(&mut self) -> Result<usize, ParseError> {
+        let column = self.next_column();
+        let idx = self.number::<usize>("wire index")?;
+        if idx >= 4 {
+            return Err(self.error_at(column, "wire index must be between 0 and 3"));
+        }
+        Ok(idx)
+    }
+    fn channel(&mut self) -> Result<String, ParseError> {
+        let (_, name) = self.expect("a channel name")?;
+        Ok(name.to_string())
+    }
+    fn gate(&mut self) -> Result<Gate, ParseError> {
+        let (column, word) = self.expect("a gate")?;
+        Gate::ALL
+            .into_iter()
+            .find(|gate| gate.name() == word)
+            .ok_or_else(|| {
+                self.error_at(
+                    column,
+                    &format!("expected And, Or, Not, or Xor, found '{}'", word),
+                )
+            })
+    }
+    fn roam(&mut self) -> Result<Roam, ParseError> {
+        let (column, word) = self.expect("a robot rule")?;
+        Roam::ALL
+            .into_iter()
+            .find(|roam| roam.name() == word)
+            .ok_or_else(|| {
+                self.error_at(
+                    column,
+                    &format!("expected Patrol, Bounce, or Chase, found '{}'", word),
+                )
+            })
+    }
+    fn color(&mut self, what: &str) -> Result<u32, ParseError> {
+        let (column, color) = self.expect(what)?;
+        u32::from_str_radix(color, 16)
+            .map_err(|_| self.error_at(column, &format!("expected a hex color, found '{}'", color)))
+    }
+    fn direction(&mut self) -> Result<Direction, ParseError> {
+        let (column, word) = self.expect("a direction")?;
+        match word {
+            "North" => Ok(Direction::North),
+            "South" => Ok(Direction::South),
+            "East" => Ok(Direction::East),
+            "West" => Ok(Direction::West),
+            _ => Err(self.error_at(
+                column,
+                &format!("expected North, South, East, or West, found '{}'", word),
+            )),
+        }
+    }
+    fn flag(&mut self, on: &str, off: &str) -> Result<bool, ParseError> {
+        let (column, word) = self.expect(&format!("'{}' or '{}'", on, off))?;
+        if word == on {
+            Ok(true)
+        } else if word == off {
+            Ok(false)
+        } else {
+            Err(self.error_at(
+                column,
+                &format!("expected '{}' or '{}', found '{}'", on, off, word),
+            ))
+        }
+    }
+    fn object(&mut self, size: Option<(usize, usize)>) -> Result<ObjectInfo, ParseError> {
+        let (column, name) = self.expect("an object")?;
+        Ok(match name {
+            "Cat" => ObjectInfo::Cat,
+            "Goal" => ObjectInfo::Goal,
+            "Box" => ObjectInfo::Box,
+            "Barrier" => ObjectInfo::Barrier,
+            "WallLeft" => ObjectInfo::WallLeft(self.flag("short", "tall")?),
+            "WallRight" => ObjectInfo::WallRight(self.flag("short", "tall")?),
+            "WallBack" => ObjectInfo::WallBack(self.flag("short", "tall")?),
+            "WallFront" => ObjectInfo::WallFront,
+            "PushButton" => ObjectInfo::PushButton(self.point(size)?, self.wire_index()?),
+            "ToggleButton" => ObjectInfo::ToggleButton(self.point(size)?, self.wire_index()?),
+            "Door" => ObjectInfo::Door(self.direction()?, self.flag("open", "closed")?),
+            "Trap" => ObjectInfo::Trap,
+            "Death" => ObjectInfo::Death,
+            "ToggleableConveyor" => {
+                ObjectInfo::ToggleableConveyor(self.direction()?, self.flag("on", "off")?)
+            }
+            "RotateableConveyor" => ObjectInfo::RotateableConveyor(
+                self.direction()?,
+                self.direction()?,
+                self.flag("on", "off")?,
+            ),
+            "BurntBox" => ObjectInfo::BurntBox,
+            "Fire" => ObjectInfo::Fire,
+            "FireOut" => ObjectInfo::FireOut,
+            "Water" => ObjectInfo::Water,
+            "Portal" => {
+                let open = self.flag("open", "closed")?;
+                let color = self.color("a portal color")?;
+                let count = self.number::<usize>("the number of portal ends")?;
+                let mut ends = vec![];
+                for _ in 0..count {
+                    ends.push(self.point(size)?);
+                }
+                ObjectInfo::Portal(ends, open, color)
+            }
+            "PushSwitch" => ObjectInfo::PushSwitch(self.channel()?),
+            "ToggleSwitch" => ObjectInfo::ToggleSwitch(self.channel()?),
+            "Gate" => {
+                let gate = self.gate()?;
+                let output = self.channel()?;
+                let count = self.number::<usize>("the number of gate inputs")?;
+                let mut inputs = vec![];
+                for _ in 0..count {
+                    inputs.push(self.channel()?);
+                }
+                ObjectInfo::Gate(gate, inputs, output)
+            }
+            "Delay" => ObjectInfo::Delay(
+                self.channel()?,
+                self.channel()?,
+                self.flag("on", "off")?,
+                self.flag("on", "off")?,
+            ),
+            "Latch" => ObjectInfo::Latch(
+                self.channel()?,
+                self.channel()?,
+                self.channel()?,
+                self.flag("on", "off")?,
+            ),
+            "Relay" => ObjectInfo::Relay(self.channel()?, self.wire_index()?),
+            "Laser" => ObjectInfo::Laser(self.direction()?),
+            "Ice" => ObjectInfo::Ice,
+            "Key" => ObjectInfo::Key(self.color("a key color")?),
+            "OneWay" => ObjectInfo::OneWay(self.direction()?),
+            "Pit" => ObjectInfo::Pit(self.flag("filled", "open")?),
+            "Robot" => ObjectInfo::Robot(self.roam()?, self.direction()?),
+            "LockedDoor" => ObjectInfo::LockedDoor(self.direction()?, self.color("a key color")?),
+            "Mirror" => ObjectInfo::Mirror(self.flag("/", "\\")?),
+            "RotateableMirror" => {
+                ObjectInfo::RotateableMirror(self.flag("/", "\\")?, self.flag("on", "off")?)
+            }
+            "LaserReceiver" => ObjectInfo::LaserReceiver(self.point(size)?, self.wire_index()?),
+            _ => {
+                return Err(self.error_at(column, &format!("unknown object '{}'", name)));
+            }
+        })
+    }
+}
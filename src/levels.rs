@@ -1,6 +1,9 @@
+use std::collections::BTreeSet;
+
 use crate::object::ObjectInfo;
+use crate::registry::LevelId;
 use crate::util::Direction;
-use crate::world::World;
+use crate::world::{Tween, World};
 
 #[turbo::serialize]
 #[derive(Copy, PartialEq)]
@@ -44,7 +47,18 @@ impl World {
                 }
             }
         }
-        return reqs;
+        reqs
+    }
+}
+
+/// Number of stars, from 1 to 3, for winning a level in this many moves
+pub fn star_rating(moves: usize, par: usize) -> usize {
+    if moves <= par {
+        3
+    } else if moves <= par + par / 2 {
+        2
+    } else {
+        1
     }
 }
 
@@ -55,64 +69,29 @@ pub enum WinState {
     Alive,
     Burnt,
     Acid,
+    Lasered,
+    Fell,
+    Caught,
     ConstructingLevel,
 }
-#[derive(Copy, Clone, PartialEq, Debug)]
-pub enum Difficulty {
-    Tutorial,
-    Easy,
-    Medium,
-    Hard,
-}
-pub const PUZZLE_PAGES: &'static [&'static [(Difficulty, &'static str)]] = &[
-    &[
-        (Difficulty::Tutorial, "Movement"),
-        (Difficulty::Tutorial, "Traps"),
-        (Difficulty::Tutorial, "Buttons"),
-        (Difficulty::Tutorial, "Box Bridge"),
-        (Difficulty::Tutorial, "Conveyor Alley"),
-    ],
-    &[
-        (Difficulty::Easy, "Easy Box"),
-        (Difficulty::Easy, "Pushing My Buttons"),
-        (Difficulty::Easy, "Acid River"),
-        (Difficulty::Easy, "Box Maze"),
-        (Difficulty::Medium, "Conveyor Loop"),
-    ],
-    &[
-        (Difficulty::Easy, "Cat Coordination"),
-        (Difficulty::Medium, "Help Me Out!"),
-        (Difficulty::Medium, "Parking Lot"),
-        (Difficulty::Medium, "Pushing My Boxes"),
-        (Difficulty::Hard, "One-way Door"),
-    ],
-    &[
-        (Difficulty::Easy, "Playing with Fire"),
-        (Difficulty::Medium, "Closet Fire"),
-        (Difficulty::Hard, "Extinguish Strategy"),
-    ],
-    &[
-        (Difficulty::Easy, "Teleportation"),
-        (Difficulty::Easy, "Glitch"),
-        (Difficulty::Medium, "Blocked Portals"),
-        (Difficulty::Medium, "Kitty Army"),
-        (Difficulty::Hard, "Mass Extinguish"),
-    ],
-    &[(Difficulty::Tutorial, "Credits"), (Difficulty::Tutorial, "Links")],
-];
-pub const PAGE_NAMES: &'static [&'static str] = &[
-    "Tutorial",
-    "Pushing boxes",
-    "Two Cat Conundrum",
-    "Factory Emergency",
-    "Portal 3",
-    "Extras",
-];
 
-const PORTAL_ORANGE: u32 = 0xd07435FF;
-const PORTAL_BLUE: u32 = 0x5199d7FF;
-const PORTAL_PURPLE: u32 = 0x874ed6FF;
-const PORTAL_GREEN: u32 = 0x49d55bFF;
+impl WinState {
+    /// Returns true if the cats died
+    pub fn is_dead(self) -> bool {
+        matches!(
+            self,
+            WinState::Burnt
+                | WinState::Acid
+                | WinState::Lasered
+                | WinState::Fell
+                | WinState::Caught
+        )
+    }
+}
+pub const PORTAL_ORANGE: u32 = 0xD07435FF;
+pub const PORTAL_BLUE: u32 = 0x5199D7FF;
+pub const PORTAL_PURPLE: u32 = 0x874ED6FF;
+pub const PORTAL_GREEN: u32 = 0x49D55BFF;
 
 pub struct LevelBuilder {
     world: World,
@@ -120,10 +99,10 @@ pub struct LevelBuilder {
 
 impl LevelBuilder {
     /// Make a new world using the floors as a 2d bool array of where the floors will be.
-    fn make_level(
+    pub fn make_level(
         width: usize,
         height: usize,
-        floors: &'static [&'static [bool]],
+        floors: &[&[bool]],
         win_requirement: WinRequirement,
     ) -> Self {
         assert_eq!(
@@ -139,12 +118,23 @@ impl LevelBuilder {
                 height,
                 inner: vec![vec![]; width * height],
                 wiring: vec![[false; 4]; width * height],
+                channels: BTreeSet::new(),
+                keys: vec![],
+                switch_cats: false,
+                selected_cat: None,
                 move_id: 0,
                 edit_history: vec![],
+                redo_history: vec![],
                 win_state: WinState::ConstructingLevel,
                 caption: "".to_string(),
                 hint: "".to_string(),
+                par: None,
+                hide_wiring: false,
+                fire_spread: None,
                 conveyance: 0,
+                events: vec![],
+                next_id: 0,
+                tick: 0,
             },
         };
         for y in 0..height {
@@ -160,14 +150,14 @@ impl LevelBuilder {
                     out.world.summon_object((x, y).into(), ObjectInfo::Barrier);
                     continue;
                 }
-                if y == height - 1 || floors[y + 1][x] == false {
+                if y == height - 1 || !floors[y + 1][x] {
                     out.world
                         .summon_object((x, y).into(), ObjectInfo::WallFront);
                 }
                 if y == 0 {
                     out.world
                         .summon_object((x, y).into(), ObjectInfo::WallBack(false));
-                } else if floors[y - 1][x] == false {
+                } else if !floors[y - 1][x] {
                     // If any floors are above, draw this wall short
                     let is_short = (0..(y - 1)).any(|i| floors[i][x]);
                     out.world
@@ -176,7 +166,7 @@ impl LevelBuilder {
                 if x == width - 1 {
                     out.world
                         .summon_object((x, y).into(), ObjectInfo::WallRight(false));
-                } else if floors[y][x + 1] == false {
+                } else if !floors[y][x + 1] {
                     // If any floors are to the right, draw this wall short
                     let is_short = (0..y).any(|i| floors[i][x + 1]);
                     out.world
@@ -185,7 +175,7 @@ impl LevelBuilder {
                 if x == 0 {
                     out.world
                         .summon_object((x, y).into(), ObjectInfo::WallLeft(true));
-                } else if floors[y][x - 1] == false {
+                } else if !floors[y][x - 1] {
                     // Is short if it is on the far left, or there is no "back" wall below it
                     let is_short = (0..y).any(|i| floors[i][x - 1]);
                     out.world
@@ -195,54 +185,79 @@ impl LevelBuilder {
         }
         out
     }
-    fn finish(mut self) -> World {
+    pub fn finish(mut self) -> World {
+        self.world.propagate_signals();
+        self.world.fire_lasers();
         self.world.edit_history.clear();
+        self.world.redo_history.clear();
         self.world.move_id = 0;
         self.world.win_state = WinState::Alive;
         self.world.conveyance = 0;
+        self.world.events.clear();
         self.world
     }
     /// Adds a caption and returns the self
-    fn with_caption(mut self, str: &'static str) -> Self {
+    pub fn with_caption(mut self, str: &str) -> Self {
         self.world.caption = String::from(str);
         self
     }
     /// Adds a hint and returns the self
-    fn with_hint(mut self, str: &'static str) -> Self {
+    pub fn with_hint(mut self, str: &str) -> Self {
         self.world.hint = String::from(str);
         self
     }
     /// Add an object to the world
-    fn with_obj(mut self, point: (i32, i32), obj: ObjectInfo) -> Self {
+    pub fn with_obj(mut self, point: (i32, i32), obj: ObjectInfo) -> Self {
         self.world.summon_object(point.into(), obj);
         self
     }
     /// Add an object to the world with a starting animation
-    fn with_obj_anim(mut self, point: (i32, i32), obj: ObjectInfo, anim: i32) -> Self {
+    pub fn with_obj_anim(mut self, point: (i32, i32), obj: ObjectInfo, anim: i32) -> Self {
         self.world.summon_object(point.into(), obj);
-        self.world[point.into()]
-            .last_mut()
-            .unwrap()
-            .animation
-            .set(anim);
+        self.world[point.into()].last_mut().unwrap().animation = Tween::new(anim);
         self
     }
     /// Set wiring in certain location
-    fn with_wiring(mut self, point: (i32, i32), idx: usize, active: bool) -> Self {
+    pub fn with_wiring(mut self, point: (i32, i32), idx: usize, active: bool) -> Self {
         self.world.set_wiring(point.into(), idx, active);
         self
     }
+    /// Set whether a channel starts on
+    pub fn with_channel(mut self, name: &str, on: bool) -> Self {
+        self.world.set_channel(name, on);
+        self
+    }
+    /// Hide the wiring overlay while the level is played
+    pub fn with_hidden_wiring(mut self) -> Self {
+        self.world.hide_wiring = true;
+        self
+    }
+    /// Only move one cat at a time, letting the player switch between them
+    pub fn with_switching_cats(mut self) -> Self {
+        self.world.switch_cats = true;
+        self
+    }
+    /// Make fire spread to neighboring boxes and goals once every this many moves
+    pub fn with_fire_spread(mut self, moves: usize) -> Self {
+        self.world.fire_spread = Some(moves);
+        self
+    }
+    /// Set the fewest moves the level can be won in
+    pub fn with_par(mut self, par: usize) -> Self {
+        self.world.par = Some(par);
+        self
+    }
     /// Add additional win requirement
-    fn with_win_req(mut self, win_requirement: WinRequirement) -> Self {
+    pub fn with_win_req(mut self, win_requirement: WinRequirement) -> Self {
         self.world.requirements.push(win_requirement);
         self
     }
-    /// Returns a template world based on the given name
-    pub fn get_template(name: &'static str) -> World {
+    /// Returns a fresh copy of the given level
+    pub fn get_template(id: LevelId) -> World {
         const T: bool = true;
         const F: bool = false;
-        match name {
-            "menu1" => Self::make_level(
+        let mut world = match id {
+            LevelId::Menu1 => Self::make_level(
                 5,
                 5,
                 &[
@@ -264,7 +279,7 @@ impl LevelBuilder {
             .with_obj((1, 3), ObjectInfo::ToggleableConveyor(Direction::North, true))
             .with_obj((1, 2), ObjectInfo::ToggleableConveyor(Direction::North, true))
             .finish(),
-            "menu2" => Self::make_level(
+            LevelId::Menu2 => Self::make_level(
                 5,
                 5,
                 &[
@@ -284,7 +299,7 @@ impl LevelBuilder {
             .with_obj((1, 2), ObjectInfo::RotateableConveyor(Direction::West, Direction::North, false))
             .with_obj((3, 1), ObjectInfo::PushButton((1,2).into(), 0))
             .finish(),
-            "menu3" => Self::make_level(
+            LevelId::Menu3 => Self::make_level(
                 5,
                 5,
                 &[
@@ -303,7 +318,7 @@ impl LevelBuilder {
             .with_obj((1, 2), ObjectInfo::Box)
             .with_obj((4, 4), ObjectInfo::Goal)
             .finish(),
-            "menu4" => Self::make_level(
+            LevelId::Menu4 => Self::make_level(
                 5,
                 5,
                 &[
@@ -325,7 +340,7 @@ impl LevelBuilder {
             .with_obj((3, 2), ObjectInfo::Death)
             .with_obj((0, 4), ObjectInfo::ToggleButton((2,2).into(),0))
             .finish(),
-            "Movement" => Self::make_level(
+            LevelId::Movement => Self::make_level(
                 6,
                 3,
                 &[
@@ -345,7 +360,7 @@ impl LevelBuilder {
                 Can you help guide him back? Press the WASD or arrow keys to move.",
             )
             .finish(),
-            "Traps" => Self::make_level(
+            LevelId::Traps => Self::make_level(
                 5,
                 3,
                 &[&[T, T, T, T, T], &[T, T, T, T, T], &[T, T, T, T, T]],
@@ -362,7 +377,7 @@ impl LevelBuilder {
                 . Press E to undo or press R to reset the level.",
             )
             .finish(),
-            "Buttons" => Self::make_level(
+            LevelId::Buttons => Self::make_level(
                 7,
                 4,
                 &[
@@ -385,7 +400,7 @@ impl LevelBuilder {
                 Circle buttons must be held down.",
             )
             .finish(),
-            "Acid River" => Self::make_level(
+            LevelId::AcidRiver => Self::make_level(
                 5,
                 5,
                 &[
@@ -410,7 +425,7 @@ impl LevelBuilder {
             .with_obj((3, 1), ObjectInfo::Box)
             .with_obj((3, 3), ObjectInfo::Box)
             .finish(),
-            "Box Bridge" => Self::make_level(
+            LevelId::BoxBridge => Self::make_level(
                 5,
                 4,
                 &[
@@ -430,7 +445,7 @@ impl LevelBuilder {
             .with_obj((4, 2), ObjectInfo::Cat)
             .with_caption("Boxes can be pushed over acid. Try to get back to your box!!")
             .finish(),
-            "Conveyor Loop" => Self::make_level(
+            LevelId::ConveyorLoop => Self::make_level(
                 6,
                 5,
                 &[
@@ -463,7 +478,7 @@ impl LevelBuilder {
             .with_obj((2,1), ObjectInfo::Goal)
             .with_obj((0,3), ObjectInfo::Cat)
             .finish(),
-            "Pushing My Buttons" => Self::make_level(
+            LevelId::PushingMyButtons => Self::make_level(
                 6,
                 5,
                 &[
@@ -486,7 +501,7 @@ impl LevelBuilder {
             .with_obj((2, 1), ObjectInfo::Box)
             .with_hint("Square buttons can be toggled")
             .finish(),
-            "Conveyor Alley" => Self::make_level(
+            LevelId::ConveyorAlley => Self::make_level(
                 6,
                 5,
                 &[
@@ -537,7 +552,7 @@ impl LevelBuilder {
             )
             .with_hint("Determine which buttons go to which conveyors. Stop them from pushing you into acid.")
             .finish(),
-            "One-way Door" => Self::make_level(
+            LevelId::OneWayDoor => Self::make_level(
                 7,
                 5,
                 &[
@@ -588,7 +603,7 @@ impl LevelBuilder {
             .with_obj((5, 3), ObjectInfo::Death)
             .with_obj((6, 3), ObjectInfo::Death)
             .finish(),
-            "Box Maze" => Self::make_level(
+            LevelId::BoxMaze => Self::make_level(
                 8,
                 7,
                 &[
@@ -613,7 +628,7 @@ impl LevelBuilder {
             .with_obj((3, 3), ObjectInfo::PushButton((4, 5).into(), 0))
             .with_obj((4, 2), ObjectInfo::PushButton((1, 1).into(), 0))
             .finish(),
-            "Conveyance Test" => Self::make_level(
+            LevelId::ConveyanceTest => Self::make_level(
                 5,
                 5,
                 &[
@@ -708,7 +723,7 @@ impl LevelBuilder {
             .with_obj((4, 4), ObjectInfo::Cat)
             .with_obj((4, 2), ObjectInfo::Goal)
             .finish(),
-            "Cat Coordination" => Self::make_level(
+            LevelId::CatCoordination => Self::make_level(
                 7,
                 4,
                 &[
@@ -736,7 +751,7 @@ impl LevelBuilder {
             )
             .with_hint("Trap the top right kitty in his box")
             .finish(),
-            "Parking Lot" => Self::make_level(
+            LevelId::ParkingLot => Self::make_level(
                 7,
                 6,
                 &[
@@ -771,7 +786,7 @@ impl LevelBuilder {
             .with_obj((6, 1), ObjectInfo::PushButton((2, 1).into(), 0))
             .with_hint("You might have to put the kitties on the trap to stop them from moving")
             .finish(),
-            "Help Me Out!" => Self::make_level(
+            LevelId::HelpMeOut => Self::make_level(
                 7,
                 4,
                 &[
@@ -795,7 +810,7 @@ impl LevelBuilder {
             .with_obj((4, 2), ObjectInfo::PushButton((1, 3).into(), 0))
             .with_obj((5, 2), ObjectInfo::PushButton((1, 2).into(), 0))
             .finish(),
-            "Pushing My Boxes" => Self::make_level(
+            LevelId::PushingMyBoxes => Self::make_level(
                 8,
                 6,
                 &[
@@ -817,7 +832,7 @@ impl LevelBuilder {
             .with_obj((2, 3), ObjectInfo::Goal)
             .with_hint("Get the goal to the bottom left corner of the right half")
             .finish(),
-            "Playing with Fire" => Self::make_level(7,5,&[
+            LevelId::PlayingWithFire => Self::make_level(7,5,&[
                 &[F,T,T,T,T,T,T],
                 &[F,T,T,T,T,T,T],
                 &[F,T,F,T,T,T,F],
@@ -837,7 +852,7 @@ impl LevelBuilder {
             bucket before it's too late! \
             Boxes and cats will burn if placed on the fire")
             .finish(),
-            "Closet Fire" => Self::make_level(7,5, &[
+            LevelId::ClosetFire => Self::make_level(7,5, &[
                 &[F,T,T,T,T,T,T],
                 &[F,T,T,T,F,F,F],
                 &[T,T,T,T,T,T,T],
@@ -869,7 +884,7 @@ impl LevelBuilder {
             .with_caption("The closet is on fire!!!!")
             .with_hint("That top right corner is awfully box shaped")
             .finish(),
-            "Extinguish Strategy" => Self::make_level(
+            LevelId::ExtinguishStrategy => Self::make_level(
                 8,
                 6,
                 &[
@@ -922,7 +937,7 @@ impl LevelBuilder {
             .with_obj((6,3), ObjectInfo::PushButton((2,3).into(), 0))
             .finish(),
 
-            "Fire test" => Self::make_level(7,7,&[
+            LevelId::FireTest => Self::make_level(7,7,&[
                 &[T,T,T,T,T,T,T],
                 &[T,T,T,T,T,T,T],
                 &[T,T,T,T,T,T,T],
@@ -942,10 +957,10 @@ impl LevelBuilder {
             .with_obj((5,5), ObjectInfo::Water)
             .with_obj((2,2), ObjectInfo::Goal)
             .finish(),
-            "one" => Self::make_level(5, 1, &[&[true, true, true, true, true]], WinRequirement::Never)
+            LevelId::One => Self::make_level(5, 1, &[&[true, true, true, true, true]], WinRequirement::Never)
                 .with_obj((4, 0), ObjectInfo::Cat)
                 .finish(),
-            "two" => Self::make_level(
+            LevelId::Two => Self::make_level(
                 5,
                 5,
                 &[
@@ -959,7 +974,7 @@ impl LevelBuilder {
             )
             .with_obj((2, 0), ObjectInfo::Cat)
             .finish(),
-            "three" => Self::make_level(
+            LevelId::Three => Self::make_level(
                 11,
                 5,
                 &[
@@ -981,7 +996,7 @@ impl LevelBuilder {
             .with_obj((9, 4), ObjectInfo::Box)
             .with_obj((8, 4), ObjectInfo::Box)
             .finish(),
-            "Easy Box" => Self::make_level(
+            LevelId::EasyBox => Self::make_level(
                 4,
                 4,
                 &[
@@ -1000,7 +1015,7 @@ impl LevelBuilder {
             .with_obj((0,2), ObjectInfo::PushButton((1,2).into(), 0))
             .with_obj((3,1), ObjectInfo::Goal)
             .finish(),
-            "Teleportation" => Self::make_level(5,5,
+            LevelId::Teleportation => Self::make_level(5,5,
             &[
                 &[T,T,T,T,T],
                 &[F,F,F,F,F],
@@ -1020,7 +1035,7 @@ impl LevelBuilder {
             .with_caption("It seems like a portal has opened in the factory! Take advantage \
                 of this new technology.")
             .finish(),
-            "Glitch" => Self::make_level(6,4,
+            LevelId::Glitch => Self::make_level(6,4,
             &[
                 &[T,T,T,T,T,T],
                 &[F,F,F,T,T,T],
@@ -1038,7 +1053,7 @@ impl LevelBuilder {
             .with_obj((0,3), ObjectInfo::ToggleButton((5,0).into(), 0))
             .with_caption("Ummmm how are you going to do this?")
             .finish(),
-            "Blocked Portals" => Self::make_level(7,5, 
+            LevelId::BlockedPortals => Self::make_level(7,5, 
             &[
                 &[T,T,T,T,T,T,T],
                 &[T,T,F,T,T,T,T],
@@ -1056,7 +1071,7 @@ impl LevelBuilder {
             .with_obj((5,2), ObjectInfo::Portal(vec![(1,3).into()],true,PORTAL_BLUE))
             .with_obj((1,3), ObjectInfo::Portal(vec![(5,2).into()],true,PORTAL_ORANGE))
             .finish(),
-            "Kitty Army" => Self::make_level(7,6,
+            LevelId::KittyArmy => Self::make_level(7,6,
                 &[
                     &[T,T,T,T,T,T,T],
                     &[T,T,T,T,T,T,T],
@@ -1094,7 +1109,7 @@ impl LevelBuilder {
             .with_obj((5,3), ObjectInfo::Portal(vec![(6,5).into(),(5,5).into()], true, PORTAL_PURPLE))
             .with_wiring((3,3),1,true)
             .finish(),
-            "Mass Extinguish" => Self::make_level(
+            LevelId::MassExtinguish => Self::make_level(
                 7,
                 6,
                 &[
@@ -1142,7 +1157,8 @@ impl LevelBuilder {
             .with_caption("Sorry, No dilly-dallying this time.\n\
                 You must finish this level in 60 moves.")
             .finish(),
-            _ => Self::make_level(1, 1, &[&[true]], WinRequirement::Never).finish(),
-        }
+        };
+        world.par = id.par();
+        world
     }
 }
@@ -1,64 +1,244 @@
 use crate::{
-    levels::{LevelBuilder, PUZZLE_PAGES, WinState},
-    menu::{Menu, button},
+    editor::Editor,
+    hint::HintSearch,
+    levels::{LevelBuilder, WinState, star_rating},
+    menu::{Menu, button, text_window},
+    registry::LevelId,
+    render::{draw_stars, play_events},
+    replay::{Playback, Replay, ReplayImport, ReplayInput},
+    save::SaveData,
     util::Direction,
-    world::World,
+    world::{World, WorldEvent},
 };
 use turbo::{time::tick, *};
 
+mod cats;
+mod editor;
+mod fire;
+mod hint;
+mod keys;
+mod laser;
+pub mod level_file;
 mod levels;
 mod menu;
 mod object;
+mod pars;
+mod registry;
+mod render;
+mod replay;
+mod robot;
+mod save;
+mod signal;
+pub mod solver;
 mod util;
+mod validate;
 mod world;
 
+#[cfg(test)]
+mod tests;
+
 #[turbo::game]
 struct GameState {
     world: World,
     menu: Menu,
     menu_world: World,
-    solved_maps: Vec<Vec<bool>>,
+    save: SaveData,
+    editor: Editor,
+    /// Inputs applied to the puzzle being played
+    replay: Replay,
+    playback: Playback,
+    replay_import: ReplayImport,
+    /// Hint for the world being played, if the player asked for one
+    hint: Option<HintSearch>,
+    /// Draw which button powers what over the world being played
+    show_wiring: bool,
+    /// Replay text shown over the world after Share is pressed
+    sharing: Option<String>,
 }
 
 impl GameState {
     fn new() -> Self {
         Self {
-            solved_maps: vec![vec![false; 8]; 10],
-            world: LevelBuilder::get_template("double_cat"),
+            save: SaveData::load(),
+            world: LevelBuilder::get_template(LevelId::Movement),
             menu: Menu::PuzzlePage(0, 0),
-            menu_world: LevelBuilder::get_template(
-                ["menu1", "menu2", "menu3", "menu4"][(random::u8() % 4) as usize],
-            ),
+            menu_world: Self::random_menu_world(),
+            editor: Editor::new(),
+            replay: Replay::new(LevelId::Movement),
+            playback: Playback::new(Replay::new(LevelId::Movement)),
+            replay_import: ReplayImport::new(),
+            hint: None,
+            show_wiring: false,
+            sharing: None,
+        }
+    }
+    fn random_menu_world() -> World {
+        LevelBuilder::get_template(
+            LevelId::MENU_LEVELS[random::u8() as usize % LevelId::MENU_LEVELS.len()],
+        )
+    }
+    /// Rebuild the world that is being played from scratch
+    fn reset_world(&mut self) {
+        self.world = match self.menu.level() {
+            Some(id) => LevelBuilder::get_template(id),
+            None => self.editor.level.build(),
+        };
+    }
+    /// Add an input to the replay if a puzzle is being played
+    fn record(&mut self, input: ReplayInput) {
+        if let Menu::World(..) = self.menu {
+            self.replay.record(input);
         }
     }
+    /// Draw the world being played with the camera centered on it
+    fn draw_world(&mut self) {
+        self.world.draw(self.show_wiring);
+        let center = World::to_screen_space(
+            (self.world.width as i32 - 1, self.world.height as i32 - 1).into(),
+        );
+        camera::set_xy(center.0 / 2 + 20, center.1 / 2 + 10);
+        self.world.check_win();
+        self.world.draw_requirements();
+    }
     fn update(&mut self) {
-        let (new_menu, world_name) = self.menu.run(&self.solved_maps);
-        self.menu = new_menu;
-        if world_name.len() != 0 {
-            if world_name == "Credits" {
-                self.menu = Menu::Credits;
-            } else if world_name == "Links" {
-                self.menu = Menu::Links;
-            } else {
-                self.world = LevelBuilder::get_template(world_name);
+        // The world waits behind the shared replay until it is closed
+        if let Some(text) = self.sharing.clone() {
+            self.draw_world();
+            if text_window("Copy this replay to share it", &text) {
+                self.sharing = None;
             }
+            return;
         }
-        if let Menu::World(page_id, puzzle_id) = self.menu {
-            self.world.draw();
-            let center = World::to_screen_space(
-                (self.world.width as i32 - 1, self.world.height as i32 - 1).into(),
+        let new_menu = self.menu.run(&self.save);
+        if new_menu != self.menu {
+            self.hint = None;
+            if let Menu::World(..) = self.menu
+                && let Some(id) = self.menu.level()
+            {
+                self.save
+                    .set_in_progress(id.name(), &self.world, &self.replay);
+                self.save.store();
+            }
+            if let Menu::World(..) = new_menu
+                && let Some(id) = new_menu.level()
+            {
+                (self.world, self.replay) = self
+                    .save
+                    .in_progress(id.name())
+                    .unwrap_or_else(|| (LevelBuilder::get_template(id), Replay::new(id)));
+            }
+        }
+        self.menu = new_menu;
+        self.world.tick = tick();
+        self.menu_world.tick = tick();
+        if self.menu == Menu::Editor {
+            if self.editor.run() {
+                self.hint = None;
+                self.menu = Menu::Playtest;
+                self.reset_world();
+            }
+        } else if self.menu == Menu::ReplayImport {
+            if let Some((replay, page_id, puzzle_id)) = self.replay_import.run() {
+                self.playback = Playback::new(replay);
+                self.world = self.playback.start_world();
+                self.menu = Menu::Replay(page_id, puzzle_id);
+            }
+        } else if let Menu::Replay(..) = self.menu {
+            self.draw_world();
+            self.playback.update(&mut self.world);
+            let speed_bounds = Bounds::with_size(50, 20)
+                .anchor_right(&turbo::screen())
+                .anchor_top(&turbo::screen())
+                .translate_y(30)
+                .translate_x(-5);
+            if button("Faster", speed_bounds, 0x777777FF, 0x888888FF)
+                || gamepad::get(0).right.just_pressed()
+            {
+                self.playback.faster();
+            }
+            if button("Slower", speed_bounds.translate_x(-55), 0x777777FF, 0x888888FF)
+                || gamepad::get(0).left.just_pressed()
+            {
+                self.playback.slower();
+            }
+            let (applied, total) = self.playback.progress();
+            let status = format!(
+                "Replay {}/{}  Speed {}",
+                applied,
+                total,
+                self.playback.speed()
+            );
+            text!(
+                status.as_str(),
+                x = speed_bounds.x() - 55,
+                y = speed_bounds.y() + 25,
+                fixed = true
             );
-            camera::set_xy(center.0 / 2 + 20, center.1 / 2 + 10);
-            self.world.check_win();
+        } else if matches!(self.menu, Menu::World(..) | Menu::Playtest) {
+            self.draw_world();
             if keyboard::get().key_e().just_pressed() || gamepad::get(0).x.just_pressed() {
                 self.world.undo();
+                self.record(ReplayInput::Undo);
+            }
+            if keyboard::get().key_q().just_pressed() || gamepad::get(0).b.just_pressed() {
+                self.world.redo();
+                self.record(ReplayInput::Redo);
             }
             if keyboard::get().key_r().just_pressed() || gamepad::get(0).y.just_pressed() {
-                self.world = LevelBuilder::get_template(PUZZLE_PAGES[page_id][puzzle_id].1);
+                self.reset_world();
+                self.record(ReplayInput::Reset);
+            }
+            let share_bounds = Bounds::with_size(50, 20)
+                .anchor_right(&turbo::screen())
+                .anchor_top(&turbo::screen())
+                .translate_y(30)
+                .translate_x(-5);
+            if let Menu::World(..) = self.menu
+                && button("Share", share_bounds, 0x777777FF, 0x888888FF)
+            {
+                self.sharing = Some(self.replay.to_string());
+            }
+            let hint_bounds = share_bounds.translate_y(25);
+            if (button("Next move", hint_bounds, 0x777777FF, 0x888888FF)
+                || keyboard::get().key_h().just_pressed())
+                && self.world.conveyance == 0
+                && self.world.win_state != WinState::Won
+            {
+                self.hint = Some(HintSearch::new(&self.world));
+            }
+            let wiring_bounds = hint_bounds.translate_y(25);
+            if !self.world.hide_wiring
+                && (button("Wiring", wiring_bounds, 0x777777FF, 0x888888FF)
+                    || keyboard::get().key_t().just_pressed())
+            {
+                self.show_wiring = !self.show_wiring;
+            }
+            if let Some(hint) = &mut self.hint {
+                if hint.is_for(&self.world) {
+                    hint.run(&self.world);
+                    hint.draw(&self.world, hint_bounds);
+                } else {
+                    self.hint = None;
+                }
             }
             let action_bounds = Bounds::with_size(100, 20).anchor_center(&turbo::screen());
             let action_background_bounds = action_bounds.above_self().adjust_height(20);
             if self.world.win_state == WinState::Won {
+                if let Some(id) = self.menu.level()
+                    && self.world.events.contains(&WorldEvent::Won)
+                {
+                    self.save.record_win(id.name(), self.world.move_id);
+                    self.save.store();
+                }
+                let best = self
+                    .menu
+                    .level()
+                    .and_then(|id| self.save.level(id.name()))
+                    .and_then(|level| level.best_moves);
+                let won_text = match best {
+                    Some(best) => format!("You won! Best: {}", best),
+                    None => "You won!".to_string(),
+                };
                 rect!(
                     bounds = action_background_bounds.expand(3),
                     color = 0x222222FF,
@@ -66,23 +246,47 @@ impl GameState {
                     border_radius = 2
                 );
                 text_box!(
-                    "You won!",
+                    &won_text,
                     bounds = action_background_bounds.translate_y(5),
                     fixed = true,
                     align = "center"
                 );
-                if button("Main Menu", action_bounds, 0x777777FF, 0x888888FF)
+                if let Some(par) = self.world.par {
+                    draw_stars(
+                        star_rating(self.world.move_id, par),
+                        action_background_bounds.x() + 32,
+                        action_background_bounds.y() + 20,
+                    );
+                }
+                let back_text = if self.menu == Menu::Playtest {
+                    "Editor"
+                } else {
+                    "Main Menu"
+                };
+                if button(back_text, action_bounds, 0x777777FF, 0x888888FF)
                     || turbo::keyboard::get().enter().just_pressed()
                     || turbo::gamepad::get(0).a.just_pressed()
                 {
-                    self.menu = Menu::PuzzlePage(page_id, puzzle_id);
+                    self.menu = match self.menu {
+                        Menu::World(page_id, puzzle_id) => Menu::PuzzlePage(page_id, puzzle_id),
+                        _ => Menu::Editor,
+                    };
+                }
+                if let Menu::World(page_id, puzzle_id) = self.menu
+                    && button(
+                        "Replay",
+                        action_bounds.translate_y(25),
+                        0x777777FF,
+                        0x888888FF,
+                    )
+                {
+                    self.playback = Playback::new(self.replay.clone());
+                    self.world = self.playback.start_world();
+                    self.menu = Menu::Replay(page_id, puzzle_id);
                 }
-                self.solved_maps[page_id][puzzle_id] = true;
             }
             // If user died
-            else if self.world.win_state == WinState::Acid
-                || self.world.win_state == WinState::Burnt
-            {
+            else if self.world.win_state.is_dead() {
                 rect!(
                     bounds = action_background_bounds.expand(3),
                     color = 0x222222FF,
@@ -99,20 +303,32 @@ impl GameState {
                     || turbo::keyboard::get().enter().just_pressed()
                     || turbo::gamepad::get(0).a.just_pressed()
                 {
-                    self.world = LevelBuilder::get_template(PUZZLE_PAGES[page_id][puzzle_id].1);
+                    self.reset_world();
+                    self.record(ReplayInput::Reset);
                 }
-                return;
             } else {
                 self.world.convey();
                 if self.world.conveyance == 0 {
-                    if turbo::gamepad::get(0).left.just_pressed() {
-                        self.world.movement(Direction::West)
+                    let dir = if turbo::gamepad::get(0).left.just_pressed() {
+                        Some(Direction::West)
                     } else if turbo::gamepad::get(0).right.just_pressed() {
-                        self.world.movement(Direction::East)
+                        Some(Direction::East)
                     } else if turbo::gamepad::get(0).up.just_pressed() {
-                        self.world.movement(Direction::North)
+                        Some(Direction::North)
                     } else if turbo::gamepad::get(0).down.just_pressed() {
-                        self.world.movement(Direction::South)
+                        Some(Direction::South)
+                    } else {
+                        None
+                    };
+                    if let Some(dir) = dir {
+                        self.world.movement(dir);
+                        self.record(ReplayInput::Move(dir));
+                    } else if self.world.active_cat().is_some()
+                        && (turbo::keyboard::get().tab().just_pressed()
+                            || turbo::gamepad::get(0).select.just_pressed())
+                    {
+                        self.world.switch_cat();
+                        self.record(ReplayInput::SwitchCat);
                     }
                 }
                 if self.world.conveyance == 1 {
@@ -128,13 +344,13 @@ impl GameState {
                     .into(),
             );
             camera::set_xy(center.0 + 95, center.1 - 30);
-            if tick() % 600 == 0 && random::u8() < 128 {
-                self.menu_world = LevelBuilder::get_template(
-                    ["menu1", "menu2", "menu3", "menu4"][(random::u8() % 4) as usize],
-                );
+            if tick().is_multiple_of(600) && random::u8() < 128 {
+                self.menu_world = Self::random_menu_world();
             }
             self.menu_world.convey();
-            if (tick() % 20 == 0 || tick() % 90 == 0) && self.menu_world.conveyance == 0 {
+            if (tick().is_multiple_of(20) || tick().is_multiple_of(90))
+                && self.menu_world.conveyance == 0
+            {
                 self.menu_world
                     .movement(Direction::array_all()[random::between(0, 3) as usize])
             }
@@ -142,7 +358,9 @@ impl GameState {
                 self.menu_world.convey();
             }
             self.menu_world.win_state = WinState::Alive;
-            self.menu_world.draw();
+            self.menu_world.draw(false);
         }
+        play_events(&mut self.world);
+        play_events(&mut self.menu_world);
     }
 }
@@ -1,6 +1,11 @@
 use std::ops::Mul;
 
-use crate::levels::{Difficulty, PAGE_NAMES, PUZZLE_PAGES};
+use crate::{
+    levels::star_rating,
+    registry::{Difficulty, LevelId, PAGE_NAMES, PUZZLE_PAGES, PageEntry},
+    render::draw_stars,
+    save::SaveData,
+};
 use turbo::*;
 
 #[derive(Copy, PartialEq)]
@@ -10,8 +15,15 @@ pub enum Menu {
     World(usize, usize),
     Credits,
     Links,
+    Editor,
+    /// Playing the level from the editor
+    Playtest,
+    /// Typing in a replay to watch
+    ReplayImport,
+    /// Watching a replay of a puzzle
+    Replay(usize, usize),
 }
-pub fn button_held(text: &'static str, bounds: Bounds, color_a: u32, color_b: u32) -> bool {
+pub fn button_held(text: &str, bounds: Bounds, color_a: u32, color_b: u32) -> bool {
     let play_color = if pointer::screen().intersects_bounds(bounds) {
         color_a
     } else {
@@ -31,14 +43,68 @@ pub fn button_held(text: &'static str, bounds: Bounds, color_a: u32, color_b: u3
         fixed = true,
         color = 0xFFFFFFFF,
     );
-    return pointer::screen().intersects_bounds(bounds) && pointer::screen().pressed();
+    pointer::screen().intersects_bounds(bounds) && pointer::screen().pressed()
 }
-pub fn button(text: &'static str, bounds: Bounds, color_a: u32, color_b: u32) -> bool {
+pub fn button(text: &str, bounds: Bounds, color_a: u32, color_b: u32) -> bool {
     button_held(text, bounds, color_a, color_b) && pointer::screen().just_pressed()
 }
+/// Draw text in a window over the screen so it can be read or copied by hand.
+/// Returns true once the window is closed.
+pub fn text_window(title: &str, text: &str) -> bool {
+    let bounds = Bounds::with_size(360, 200).anchor_center(&turbo::screen());
+    rect!(
+        bounds = bounds.expand(6),
+        color = 0x111111EE,
+        fixed = true,
+        border_radius = 2
+    );
+    text_box!(
+        title,
+        bounds = bounds.height(12),
+        align = "center",
+        fixed = true,
+    );
+    rect!(
+        bounds = bounds.translate_y(16).height(156),
+        color = 0x222222FF,
+        fixed = true,
+        border_radius = 2
+    );
+    text_box!(
+        text,
+        bounds = bounds.translate_y(16).height(156).inset(3),
+        fixed = true,
+    );
+    let close_bounds = Bounds::with_size(50, 20)
+        .anchor_center(&bounds)
+        .anchor_bottom(&bounds);
+    button("Close", close_bounds, 0x777777FF, 0x888888FF) || keyboard::get().escape().just_pressed()
+}
 
 impl Menu {
-    pub fn run(&self, completed: &Vec<Vec<bool>>) -> (Menu, &'static str) {
+    /// Menu opened by picking an entry on a puzzle page
+    fn open(entry: PageEntry, page_id: usize, puzzle_id: usize) -> Menu {
+        match entry {
+            PageEntry::Level(_) => Menu::World(page_id, puzzle_id),
+            PageEntry::Editor => Menu::Editor,
+            PageEntry::WatchReplay => Menu::ReplayImport,
+            PageEntry::Credits => Menu::Credits,
+            PageEntry::Links => Menu::Links,
+        }
+    }
+    /// Level being played or watched
+    pub fn level(&self) -> Option<LevelId> {
+        match self {
+            Menu::World(page_id, puzzle_id) | Menu::Replay(page_id, puzzle_id) => {
+                match PUZZLE_PAGES[*page_id][*puzzle_id] {
+                    PageEntry::Level(id) => Some(id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+    pub fn run(&self, save: &SaveData) -> Menu {
         match self {
             Menu::PuzzlePage(page_id, selected) => {
                 let display_bounds = Bounds::with_size(100, 20)
@@ -55,14 +121,14 @@ impl Menu {
                     .anchor_center(&turbo::screen())
                     .translate_x(150)
                     .translate_y(-60);
-                let puzzle_names = PUZZLE_PAGES[*page_id];
+                let entries = PUZZLE_PAGES[*page_id];
                 text_box!(
                     PAGE_NAMES[*page_id],
                     bounds = top_bounds.translate_y(-30),
                     align = "center",
                     fixed = true,
                 );
-                for i in 0..puzzle_names.len() {
+                for (i, entry) in entries.iter().enumerate() {
                     let bounds = top_bounds.translate_y(i * 30);
                     if *selected == i {
                         let color = 0x282828FF
@@ -75,7 +141,7 @@ impl Menu {
                             border_radius = 2
                         );
                     }
-                    let (color_a, color_b) = if completed[*page_id][i] {
+                    let (color_a, color_b) = if save.solved(entry.label()) {
                         if *selected == i {
                             (0x3fb84aff, 0x3fb84aff)
                         } else {
@@ -88,10 +154,10 @@ impl Menu {
                             (0x777777FF, 0x888888FF)
                         }
                     };
-                    let (diff_color, difficulty_char) = match puzzle_names[i].0 {
-                        Difficulty::Easy => (0x3fb84aFF, "Easy"),
-                        Difficulty::Medium => (0xcbb41cFF, "Med"),
-                        Difficulty::Hard => (0xbc4040FF, "Hard"),
+                    let (diff_color, difficulty_char) = match entry.difficulty() {
+                        Difficulty::Easy => (0x3FB84AFF, "Easy"),
+                        Difficulty::Medium => (0xCBB41CFF, "Med"),
+                        Difficulty::Hard => (0xBC4040FF, "Hard"),
                         Difficulty::Tutorial => (0x00000000, " "),
                     };
                     rect!(
@@ -110,8 +176,15 @@ impl Menu {
                         fixed = true,
                         align = "center"
                     );
-                    if button(puzzle_names[i].1, bounds, color_a, color_b) {
-                        return (Menu::World(*page_id, i), PUZZLE_PAGES[*page_id][i].1);
+                    let best = save.level(entry.label()).and_then(|v| v.best_moves);
+                    if let PageEntry::Level(id) = *entry
+                        && let Some(best) = best
+                        && let Some(par) = id.par()
+                    {
+                        draw_stars(star_rating(best, par), bounds.x() - 40, bounds.y() + 5);
+                    }
+                    if button(entry.label(), bounds, color_a, color_b) {
+                        return Menu::open(*entry, *page_id, i);
                     }
                 }
                 let right_bounds = Bounds::with_size(45, 20)
@@ -119,48 +192,37 @@ impl Menu {
                     .translate_x(180)
                     .translate_y(4 * 30);
                 let left_bounds = right_bounds.translate_x(-60);
-                let mut out = (Menu::PuzzlePage(1000, 1000), "");
+                let mut out = Menu::PuzzlePage(1000, 1000);
                 if *page_id == PUZZLE_PAGES.len() - 1 {
                     button("Next", right_bounds, 0x444444FF, 0x444444FF);
                 } else if button("Next", right_bounds, 0x777777FF, 0x888888FF) {
-                    out = (Menu::PuzzlePage(*page_id + 1, 0), "");
+                    out = Menu::PuzzlePage(*page_id + 1, 0);
                 }
                 if *page_id == 0 {
                     button("Prev", left_bounds, 0x444444FF, 0x444444FF);
                 } else if button("Prev", left_bounds, 0x777777FF, 0x888888FF) {
-                    out = (Menu::PuzzlePage(*page_id - 1, 0), "");
+                    out = Menu::PuzzlePage(*page_id - 1, 0);
                 }
-                if out != (Menu::PuzzlePage(1000, 1000), "") {
+                if out != Menu::PuzzlePage(1000, 1000) {
                     return out;
                 }
-                if turbo::gamepad::get(0).up.just_pressed() {
-                    if *selected != 0 {
-                        return (Menu::PuzzlePage(*page_id, (*selected).max(1) - 1), "");
-                    }
+                if turbo::gamepad::get(0).up.just_pressed() && *selected != 0 {
+                    return Menu::PuzzlePage(*page_id, (*selected).max(1) - 1);
                 }
                 if turbo::gamepad::get(0).down.just_pressed() {
-                    return (
-                        Menu::PuzzlePage(*page_id, (*selected + 1).min(puzzle_names.len() - 1)),
-                        "",
-                    );
+                    return Menu::PuzzlePage(*page_id, (*selected + 1).min(entries.len() - 1));
                 }
                 if turbo::gamepad::get(0).right.just_pressed() {
-                    return (
-                        Menu::PuzzlePage((*page_id + 1).min(PUZZLE_PAGES.len() - 1), 0),
-                        "",
-                    );
+                    return Menu::PuzzlePage((*page_id + 1).min(PUZZLE_PAGES.len() - 1), 0);
                 }
                 if turbo::gamepad::get(0).left.just_pressed() {
-                    return (Menu::PuzzlePage((*page_id).max(1) - 1, 0), "");
+                    return Menu::PuzzlePage((*page_id).max(1) - 1, 0);
                 }
                 if turbo::gamepad::get(0).a.just_pressed()
                     || turbo::keyboard::get().enter().just_pressed()
                     || turbo::keyboard::get().key_e().just_pressed()
                 {
-                    return (
-                        Menu::World(*page_id, *selected as usize),
-                        PUZZLE_PAGES[*page_id][*selected].1,
-                    );
+                    return Menu::open(entries[*selected], *page_id, *selected);
                 }
             }
             Menu::World(page_id, world_id) => {
@@ -168,7 +230,36 @@ impl Menu {
                     || turbo::keyboard::get().escape().just_pressed()
                     || gamepad::get(0).start.just_pressed()
                 {
-                    return (Menu::PuzzlePage(*page_id, *world_id), "");
+                    return Menu::PuzzlePage(*page_id, *world_id);
+                }
+            }
+            Menu::Editor => {
+                // Escape is left alone so it can be used while typing a caption
+                if button("Exit", Bounds::new(2, 2, 30, 20), 0x777777FF, 0x888888FF) {
+                    return Menu::PuzzlePage(PUZZLE_PAGES.len() - 1, 0);
+                }
+            }
+            Menu::Playtest => {
+                if button("Exit", Bounds::new(2, 2, 30, 20), 0x777777FF, 0x888888FF)
+                    || turbo::keyboard::get().escape().just_pressed()
+                    || gamepad::get(0).start.just_pressed()
+                {
+                    return Menu::Editor;
+                }
+            }
+            Menu::ReplayImport => {
+                if button("Exit", Bounds::new(2, 2, 30, 20), 0x777777FF, 0x888888FF)
+                    || turbo::keyboard::get().escape().just_pressed()
+                {
+                    return Menu::PuzzlePage(PUZZLE_PAGES.len() - 1, 0);
+                }
+            }
+            Menu::Replay(page_id, world_id) => {
+                if button("Exit", Bounds::new(2, 2, 30, 20), 0x777777FF, 0x888888FF)
+                    || turbo::keyboard::get().escape().just_pressed()
+                    || gamepad::get(0).start.just_pressed()
+                {
+                    return Menu::PuzzlePage(*page_id, *world_id);
                 }
             }
             Menu::Credits => {
@@ -176,7 +267,7 @@ impl Menu {
                     || turbo::keyboard::get().escape().just_pressed()
                     || gamepad::get(0).start.just_pressed()
                 {
-                    return (Menu::PuzzlePage(PUZZLE_PAGES.len() - 1, 0), "");
+                    return Menu::PuzzlePage(PUZZLE_PAGES.len() - 1, 0);
                 }
                 text_box!(
                     "Credits\n\nBenjamin Cates --> Lead programmer, level designer, artist\
@@ -194,7 +285,7 @@ impl Menu {
                     || turbo::keyboard::get().escape().just_pressed()
                     || gamepad::get(0).start.just_pressed()
                 {
-                    return (Menu::PuzzlePage(PUZZLE_PAGES.len() - 1, 0), "");
+                    return Menu::PuzzlePage(PUZZLE_PAGES.len() - 1, 0);
                 }
                 text_box!(
                     "GitHub: https://github.com/benjamin-cates/cat_factory\n\n\
@@ -206,6 +297,6 @@ impl Menu {
                 );
             }
         }
-        return (*self, "");
+        *self
     }
 }
@@ -1,20 +1,24 @@
-use turbo::{time::tick, *};
-
 use crate::{
+    robot::Roam,
+    signal::Gate,
     util::{Direction, Point},
-    world::World,
+    world::{Tween, World},
 };
 
 #[turbo::serialize]
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Hash)]
 pub enum ObjectInfo {
     Cat,
     Goal,
     Box,
     Barrier,
+    /// Wall along the west edge of the cell that nothing can cross. Contains true if drawn short.
     WallLeft(bool),
+    /// Wall along the east edge of the cell that nothing can cross. Contains true if drawn short.
     WallRight(bool),
+    /// Wall along the north edge of the cell that nothing can cross. Contains true if drawn short.
     WallBack(bool),
+    /// Wall along the south edge of the cell that nothing can cross
     WallFront,
     PushButton(Point, usize),
     ToggleButton(Point, usize),
@@ -28,16 +32,61 @@ pub enum ObjectInfo {
     FireOut,
     Water,
     Portal(Vec<Point>, bool, u32),
+    /// Holds a channel on while something is on it
+    PushSwitch(String),
+    /// Flips a channel each time something is moved onto it
+    ToggleSwitch(String),
+    /// Contains (gate, input channels, output channel)
+    Gate(Gate, Vec<String>, String),
+    /// Contains (input, output, input at the end of the last move, on).
+    /// The output follows the input one move late.
+    Delay(String, String, bool, bool),
+    /// Contains (set, reset, output, on).
+    /// Turns on while set is on, off while reset is on, and otherwise stays the same.
+    Latch(String, String, String, bool),
+    /// Drives one of the four wires of its own cell from a channel.
+    /// Contains (channel, wire index)
+    Relay(String, usize),
+    /// Fires a beam in the direction
+    Laser(Direction),
+    /// Pushable mirror that bounces beams. Contains true for a / mirror and false for a \ mirror.
+    Mirror(bool),
+    /// Fixed mirror that flips while its wires are powered.
+    /// Contains (true for a / mirror when unpowered, powered)
+    RotateableMirror(bool, bool),
+    /// Powers a wire while a beam reaches it. Contains (wire target, wire index)
+    LaserReceiver(Point, usize),
+    /// Floor that cats, boxes, goals, and water slide across until something stops them
+    Ice,
+    /// Picked up by the first cat to reach it. Contains the color of the doors it opens.
+    Key(u32),
+    /// Door that a cat opens by walking into it with a key of the same color, using up the key.
+    /// Contains (direction, color)
+    LockedDoor(Direction, u32),
+    /// Gate that objects can only move onto while moving in its direction
+    OneWay(Direction),
+    /// Hole that cats fall into. The first box pushed in fills it, leaving floor behind.
+    /// Contains true once it has been filled
+    Pit(bool),
+    /// Enemy that takes a step after every move and catches any cat it meets.
+    /// Contains (rule it moves by, direction it is heading)
+    Robot(Roam, Direction),
 }
 
+/// Identifies an object for as long as it exists, wherever it is in its cell's stack
+#[turbo::serialize]
+#[derive(Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId(pub u32);
+
 #[turbo::serialize]
 #[derive(PartialEq)]
 pub struct Object {
+    pub id: ObjectId,
     pub obj_type: ObjectInfo,
-    pub draw_pos: (Tween<i32>, Tween<i32>),
+    pub draw_pos: (Tween, Tween),
     pub facing: Direction,
     pub position: Point,
-    pub animation: Tween<i32>,
+    pub animation: Tween,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -48,180 +97,8 @@ pub enum MoveType {
 }
 
 impl Object {
-    pub fn draw_height(&self) -> i32 {
-        match self.obj_type {
-            ObjectInfo::Trap => -500,
-            ObjectInfo::Cat => 500,
-            ObjectInfo::Goal => 499,
-            ObjectInfo::Box => 500,
-            ObjectInfo::Barrier => 0,
-            ObjectInfo::WallLeft(_) => 1500,
-            ObjectInfo::WallRight(_) => -2000,
-            ObjectInfo::WallBack(_) => -500,
-            ObjectInfo::WallFront => 2000,
-            ObjectInfo::PushButton(..) => -2000,
-            ObjectInfo::ToggleButton(..) => -2000,
-            ObjectInfo::Door(..) => 510,
-            ObjectInfo::Death => -500,
-            ObjectInfo::ToggleableConveyor(..) => -500,
-            ObjectInfo::RotateableConveyor(..) => -500,
-            ObjectInfo::Water => 500,
-            ObjectInfo::Fire => -500,
-            ObjectInfo::BurntBox => 500,
-            ObjectInfo::FireOut => -500,
-            ObjectInfo::Portal(..) => -500,
-        }
-    }
-    pub fn draw(&mut self) {
-        let x = self.draw_pos.0.get() as i32;
-        let y = self.draw_pos.1.get() as i32;
-        let anim = self.animation.get();
-        match self.obj_type {
-            // OBJECTS
-            ObjectInfo::Box => sprite!("box", x = x - 1, y = y - 11),
-            ObjectInfo::Cat => {
-                sprite!(
-                    "house/cat",
-                    x = x + 5,
-                    y = y - 10,
-                    opacity = (1.0 - anim as f32 / 8.0).max(0.0)
-                );
-            }
-            ObjectInfo::Goal => {
-                if anim == 0 {
-                    sprite!("goal", x = x, y = y - 16)
-                } else if anim == 1 || anim == 2 {
-                    sprite!("goal2", x = x, y = y - 16)
-                } else if anim == 3 || anim == 4 {
-                    sprite!("goal3", x = x, y = y - 16)
-                } else if anim == 5 || anim == 6 {
-                    sprite!("goal4", x = x, y = y - 16)
-                } else if anim == 7 || anim == 8 {
-                    sprite!("goal5", x = x, y = y - 16)
-                } else {
-                    sprite!("goal6", x = x, y = y - 16 - (anim - 8) * 12)
-                }
-            }
-            // TRAPS
-            ObjectInfo::Trap => sprite!("trap2", x = x, y = y,),
-            ObjectInfo::Death => {
-                if tick() % 30 < 15 {
-                    sprite!("factory/acid", x = x, y = y)
-                } else {
-                    sprite!("factory/acid_1", x = x, y = y)
-                }
-            }
-            ObjectInfo::Fire => {
-                sprite!(
-                    "factory/fire",
-                    frame = ((tick() as f32 + y as f32 * 10.0 + x as f32 * 5.0 + x as f32 % 51.0)
-                        / 10.0) as usize,
-                    x = x + 5,
-                    y = y - 25
-                )
-            }
-            ObjectInfo::BurntBox => {
-                sprite!(
-                    "factory/smoke",
-                    x = World::to_screen_space(self.position).0,
-                    y = World::to_screen_space(self.position).1 - anim * 5,
-                    opacity = 1.0 - anim as f32 / 10.0
-                )
-            }
-            ObjectInfo::FireOut => {
-                sprite!("factory/charcoal", x = x, y = y)
-            }
-            ObjectInfo::Water => {
-                sprite!("factory/water_bucket", x = x, y = y - 10)
-            }
-
-            // WALLS
-            ObjectInfo::Barrier => {}
-            ObjectInfo::WallBack(true) => sprite!("factory/front_wall", x = x, y = y - 3),
-            ObjectInfo::WallBack(false) => sprite!("factory/back_wall2", x = x, y = (y - 32)),
-            ObjectInfo::WallFront => sprite!("factory/front_wall", x = x + 14, y = y + 25),
-            ObjectInfo::WallLeft(true) => sprite!("factory/left_wall", x = x, y = y - 32),
-            ObjectInfo::WallLeft(false) => sprite!("factory/right_wall", x = x, y = y - 27),
-            ObjectInfo::WallRight(true) => {
-                sprite!("factory/right_wall_short", x = x + 37, y = y - 34)
-            }
-            ObjectInfo::WallRight(false) => sprite!("factory/right_wall", x = x + 38, y = y - 27),
-
-            // BUTTONS
-            ObjectInfo::PushButton(..) => {
-                if anim == 0 {
-                    sprite!("house/push_button_open", x = x, y = y)
-                } else {
-                    sprite!("house/push_button", x = x, y = y)
-                }
-            }
-            ObjectInfo::ToggleButton(..) => {
-                if anim == 0 {
-                    sprite!("house/toggle_button_open", x = x, y = y)
-                } else {
-                    sprite!("house/toggle_button", x = x, y = y)
-                }
-            }
-
-            // DOORS
-            ObjectInfo::Door(Direction::South | Direction::North, _) => {
-                if anim == 0 {
-                    sprite!("factory/door_vertical_closed", x = x + 19, y = y - 17)
-                } else if anim == 1 {
-                    sprite!("factory/door_vertical_middle", x = x + 19, y = y - 17)
-                } else if anim == 2 {
-                    sprite!("factory/door_vertical_open", x = x + 19, y = y - 17)
-                }
-            }
-            ObjectInfo::Door(Direction::East | Direction::West, _) => {
-                if anim == 0 {
-                    sprite!("factory/door_horizontal_closed", x = x + 6, y = y - 7)
-                } else if anim == 1 {
-                    sprite!("factory/door_horizontal_middle", x = x + 6, y = y - 7)
-                } else if anim == 2 {
-                    sprite!("factory/door_horizontal_open", x = x + 6, y = y - 7)
-                }
-            }
-            // CONVEYOR BELTS
-            ObjectInfo::RotateableConveyor(dir, phant, false)
-            | ObjectInfo::RotateableConveyor(phant, dir, true) => {
-                match dir {
-                    Direction::North => sprite!("factory/conveyor_up", x = x, y = y),
-                    Direction::South => sprite!("factory/conveyor_down", x = x, y = y),
-                    Direction::East => sprite!("factory/conveyor_right", x = x, y = y),
-                    Direction::West => sprite!("factory/conveyor_left", x = x, y = y),
-                }
-                match phant {
-                    Direction::North => sprite!("factory/phantom_up", x = x, y = y),
-                    Direction::South => sprite!("factory/phantom_down", x = x, y = y),
-                    Direction::East => sprite!("factory/phantom_right", x = x, y = y),
-                    Direction::West => sprite!("factory/phantom_left", x = x, y = y),
-                }
-            }
-            ObjectInfo::ToggleableConveyor(phant, false) => {
-                sprite!("factory/conveyor_empty", x = x, y = y);
-                match phant {
-                    Direction::North => sprite!("factory/phantom_up", x = x, y = y),
-                    Direction::South => sprite!("factory/phantom_down", x = x, y = y),
-                    Direction::East => sprite!("factory/phantom_right", x = x, y = y),
-                    Direction::West => sprite!("factory/phantom_left", x = x, y = y),
-                }
-            }
-            ObjectInfo::ToggleableConveyor(dir, true) => match dir {
-                Direction::North => sprite!("factory/conveyor_up", x = x, y = y),
-                Direction::South => sprite!("factory/conveyor_down", x = x, y = y),
-                Direction::East => sprite!("factory/conveyor_right", x = x, y = y),
-                Direction::West => sprite!("factory/conveyor_left", x = x, y = y),
-            },
-
-            //PORTALS
-            ObjectInfo::Portal(_, false, _) => sprite!("factory/portal_closed", x = x, y = y),
-            ObjectInfo::Portal(_, true, color) => {
-                sprite!("factory/portal_open", color = color, x = x, y = y)
-            }
-        }
-    }
-    pub fn test_push_by(&self, pusher: &ObjectInfo) -> MoveType {
+    /// How the object reacts to the pusher moving onto its cell in the direction dir
+    pub fn test_push_by(&self, pusher: &ObjectInfo, dir: Direction) -> MoveType {
         match self.obj_type {
             ObjectInfo::RotateableConveyor(..) => MoveType::MoveOver,
             ObjectInfo::ToggleableConveyor(..) => MoveType::MoveOver,
@@ -248,10 +125,57 @@ impl Object {
             ObjectInfo::BurntBox => MoveType::MoveOver,
             ObjectInfo::FireOut => MoveType::MoveOver,
             ObjectInfo::Portal(..) => MoveType::MoveOver,
+            ObjectInfo::PushSwitch(_) | ObjectInfo::ToggleSwitch(_) => MoveType::MoveOver,
+            ObjectInfo::Gate(..)
+            | ObjectInfo::Delay(..)
+            | ObjectInfo::Latch(..)
+            | ObjectInfo::Relay(..) => MoveType::MoveOver,
+            ObjectInfo::Mirror(_) => MoveType::Push,
+            ObjectInfo::Laser(_)
+            | ObjectInfo::RotateableMirror(..)
+            | ObjectInfo::LaserReceiver(..) => MoveType::NotAllowed,
+            ObjectInfo::Ice => MoveType::MoveOver,
+            ObjectInfo::Key(_) => MoveType::MoveOver,
+            ObjectInfo::LockedDoor(..) => MoveType::NotAllowed,
+            ObjectInfo::Pit(_) => MoveType::MoveOver,
+            ObjectInfo::Robot(..) => {
+                if *pusher == ObjectInfo::Cat {
+                    MoveType::MoveOver
+                } else {
+                    MoveType::NotAllowed
+                }
+            }
+            ObjectInfo::OneWay(allowed) => {
+                if dir == allowed {
+                    MoveType::MoveOver
+                } else {
+                    MoveType::NotAllowed
+                }
+            }
+        }
+    }
+    /// Edge of the cell the object walls off, if it is a wall
+    pub fn wall_edge(&self) -> Option<Direction> {
+        match self.obj_type {
+            ObjectInfo::WallLeft(_) => Some(Direction::West),
+            ObjectInfo::WallRight(_) => Some(Direction::East),
+            ObjectInfo::WallBack(_) => Some(Direction::North),
+            ObjectInfo::WallFront => Some(Direction::South),
+            _ => None,
         }
     }
+    /// Returns true if the object keeps going when it is moved onto ice
+    pub fn slides(&self) -> bool {
+        matches!(
+            self.obj_type,
+            ObjectInfo::Cat | ObjectInfo::Box | ObjectInfo::Goal | ObjectInfo::Water
+        )
+    }
     pub fn does_move(&self, world: &World) -> bool {
         if self.obj_type == ObjectInfo::Cat {
+            if world.active_cat().is_some_and(|id| id != self.id) {
+                return false;
+            }
             if world[self.position]
                 .iter()
                 .any(|v| v.obj_type == ObjectInfo::Trap)
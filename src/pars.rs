@@ -0,0 +1,30 @@
+//! Fewest moves each puzzle can be won in, as found by the solver.
+//! Generated by `cargo test --release -- --ignored write_pars`; don't edit by hand.
+
+use crate::registry::LevelId;
+
+pub const PARS: &[(LevelId, usize)] = &[
+    (LevelId::Movement, 7),
+    (LevelId::Traps, 10),
+    (LevelId::Buttons, 16),
+    (LevelId::BoxBridge, 18),
+    (LevelId::ConveyorAlley, 9),
+    (LevelId::EasyBox, 12),
+    (LevelId::PushingMyButtons, 32),
+    (LevelId::AcidRiver, 18),
+    (LevelId::BoxMaze, 46),
+    (LevelId::ConveyorLoop, 36),
+    (LevelId::CatCoordination, 25),
+    (LevelId::HelpMeOut, 13),
+    (LevelId::ParkingLot, 16),
+    (LevelId::PushingMyBoxes, 42),
+    (LevelId::OneWayDoor, 28),
+    (LevelId::PlayingWithFire, 54),
+    (LevelId::ClosetFire, 34),
+    (LevelId::ExtinguishStrategy, 26),
+    (LevelId::Teleportation, 19),
+    (LevelId::Glitch, 19),
+    (LevelId::BlockedPortals, 22),
+    (LevelId::KittyArmy, 26),
+    (LevelId::MassExtinguish, 28),
+];
@@ -0,0 +1,298 @@
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use crate::pars::PARS;
+
+/// Every level built into the game.
+/// Levels are stored and shared by `name`, so names must never change once a level ships;
+/// add an entry to `RENAMED_LEVELS` in the save module if one has to.
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
+pub enum LevelId {
+    Menu1,
+    Menu2,
+    Menu3,
+    Menu4,
+    Movement,
+    Traps,
+    Buttons,
+    BoxBridge,
+    ConveyorAlley,
+    EasyBox,
+    PushingMyButtons,
+    AcidRiver,
+    BoxMaze,
+    ConveyorLoop,
+    CatCoordination,
+    HelpMeOut,
+    ParkingLot,
+    PushingMyBoxes,
+    OneWayDoor,
+    PlayingWithFire,
+    ClosetFire,
+    ExtinguishStrategy,
+    Teleportation,
+    Glitch,
+    BlockedPortals,
+    KittyArmy,
+    MassExtinguish,
+    ConveyanceTest,
+    FireTest,
+    One,
+    Two,
+    Three,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Difficulty {
+    Tutorial,
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// What a level is for
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LevelKind {
+    /// Shipped on the puzzle pages
+    Puzzle(Difficulty),
+    /// Played in the background of the puzzle pages
+    Menu,
+    /// Used while developing mechanics, never shown to players
+    Test,
+}
+
+impl LevelId {
+    pub const ALL: &'static [LevelId] = &[
+        LevelId::Menu1,
+        LevelId::Menu2,
+        LevelId::Menu3,
+        LevelId::Menu4,
+        LevelId::Movement,
+        LevelId::Traps,
+        LevelId::Buttons,
+        LevelId::BoxBridge,
+        LevelId::ConveyorAlley,
+        LevelId::EasyBox,
+        LevelId::PushingMyButtons,
+        LevelId::AcidRiver,
+        LevelId::BoxMaze,
+        LevelId::ConveyorLoop,
+        LevelId::CatCoordination,
+        LevelId::HelpMeOut,
+        LevelId::ParkingLot,
+        LevelId::PushingMyBoxes,
+        LevelId::OneWayDoor,
+        LevelId::PlayingWithFire,
+        LevelId::ClosetFire,
+        LevelId::ExtinguishStrategy,
+        LevelId::Teleportation,
+        LevelId::Glitch,
+        LevelId::BlockedPortals,
+        LevelId::KittyArmy,
+        LevelId::MassExtinguish,
+        LevelId::ConveyanceTest,
+        LevelId::FireTest,
+        LevelId::One,
+        LevelId::Two,
+        LevelId::Three,
+    ];
+    pub const MENU_LEVELS: &'static [LevelId] = &[
+        LevelId::Menu1,
+        LevelId::Menu2,
+        LevelId::Menu3,
+        LevelId::Menu4,
+    ];
+    /// Stable name used in save data, replays, and on the puzzle pages
+    pub fn name(self) -> &'static str {
+        match self {
+            LevelId::Menu1 => "menu1",
+            LevelId::Menu2 => "menu2",
+            LevelId::Menu3 => "menu3",
+            LevelId::Menu4 => "menu4",
+            LevelId::Movement => "Movement",
+            LevelId::Traps => "Traps",
+            LevelId::Buttons => "Buttons",
+            LevelId::BoxBridge => "Box Bridge",
+            LevelId::ConveyorAlley => "Conveyor Alley",
+            LevelId::EasyBox => "Easy Box",
+            LevelId::PushingMyButtons => "Pushing My Buttons",
+            LevelId::AcidRiver => "Acid River",
+            LevelId::BoxMaze => "Box Maze",
+            LevelId::ConveyorLoop => "Conveyor Loop",
+            LevelId::CatCoordination => "Cat Coordination",
+            LevelId::HelpMeOut => "Help Me Out!",
+            LevelId::ParkingLot => "Parking Lot",
+            LevelId::PushingMyBoxes => "Pushing My Boxes",
+            LevelId::OneWayDoor => "One-way Door",
+            LevelId::PlayingWithFire => "Playing with Fire",
+            LevelId::ClosetFire => "Closet Fire",
+            LevelId::ExtinguishStrategy => "Extinguish Strategy",
+            LevelId::Teleportation => "Teleportation",
+            LevelId::Glitch => "Glitch",
+            LevelId::BlockedPortals => "Blocked Portals",
+            LevelId::KittyArmy => "Kitty Army",
+            LevelId::MassExtinguish => "Mass Extinguish",
+            LevelId::ConveyanceTest => "Conveyance Test",
+            LevelId::FireTest => "Fire test",
+            LevelId::One => "one",
+            LevelId::Two => "two",
+            LevelId::Three => "three",
+        }
+    }
+    pub fn kind(self) -> LevelKind {
+        match self {
+            LevelId::Menu1 | LevelId::Menu2 | LevelId::Menu3 | LevelId::Menu4 => LevelKind::Menu,
+            LevelId::Movement
+            | LevelId::Traps
+            | LevelId::Buttons
+            | LevelId::BoxBridge
+            | LevelId::ConveyorAlley => LevelKind::Puzzle(Difficulty::Tutorial),
+            LevelId::EasyBox
+            | LevelId::PushingMyButtons
+            | LevelId::AcidRiver
+            | LevelId::BoxMaze
+            | LevelId::CatCoordination
+            | LevelId::PlayingWithFire
+            | LevelId::Teleportation
+            | LevelId::Glitch => LevelKind::Puzzle(Difficulty::Easy),
+            LevelId::ConveyorLoop
+            | LevelId::HelpMeOut
+            | LevelId::ParkingLot
+            | LevelId::PushingMyBoxes
+            | LevelId::ClosetFire
+            | LevelId::BlockedPortals
+            | LevelId::KittyArmy => LevelKind::Puzzle(Difficulty::Medium),
+            LevelId::OneWayDoor | LevelId::ExtinguishStrategy | LevelId::MassExtinguish => {
+                LevelKind::Puzzle(Difficulty::Hard)
+            }
+            LevelId::ConveyanceTest
+            | LevelId::FireTest
+            | LevelId::One
+            | LevelId::Two
+            | LevelId::Three => LevelKind::Test,
+        }
+    }
+    /// Fewest moves the level can be won in, if the solver has been run on it
+    pub fn par(self) -> Option<usize> {
+        PARS.iter().find(|(id, _)| *id == self).map(|(_, par)| *par)
+    }
+    /// (page, position) of the level on the puzzle pages
+    pub fn page_position(self) -> Option<(usize, usize)> {
+        PUZZLE_PAGES.iter().enumerate().find_map(|(page_id, page)| {
+            page.iter()
+                .position(|entry| *entry == PageEntry::Level(self))
+                .map(|puzzle_id| (page_id, puzzle_id))
+        })
+    }
+}
+
+impl FromStr for LevelId {
+    type Err = UnknownLevel;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        LevelId::ALL
+            .iter()
+            .copied()
+            .find(|id| id.name() == name)
+            .ok_or_else(|| UnknownLevel(name.to_string()))
+    }
+}
+
+impl Display for LevelId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str(self.name())
+    }
+}
+
+/// A level name that isn't in the registry
+#[derive(Clone, PartialEq, Debug)]
+pub struct UnknownLevel(pub String);
+
+impl Display for UnknownLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_fmt(format_args!("There is no level named {}", self.0))
+    }
+}
+
+/// Something that can be picked from a puzzle page
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PageEntry {
+    Level(LevelId),
+    Editor,
+    WatchReplay,
+    Credits,
+    Links,
+}
+
+impl PageEntry {
+    pub fn label(self) -> &'static str {
+        match self {
+            PageEntry::Level(id) => id.name(),
+            PageEntry::Editor => "Level Editor",
+            PageEntry::WatchReplay => "Watch Replay",
+            PageEntry::Credits => "Credits",
+            PageEntry::Links => "Links",
+        }
+    }
+    pub fn difficulty(self) -> Difficulty {
+        match self {
+            PageEntry::Level(id) => match id.kind() {
+                LevelKind::Puzzle(difficulty) => difficulty,
+                LevelKind::Menu | LevelKind::Test => Difficulty::Tutorial,
+            },
+            _ => Difficulty::Tutorial,
+        }
+    }
+}
+
+pub const PUZZLE_PAGES: &[&[PageEntry]] = &[
+    &[
+        PageEntry::Level(LevelId::Movement),
+        PageEntry::Level(LevelId::Traps),
+        PageEntry::Level(LevelId::Buttons),
+        PageEntry::Level(LevelId::BoxBridge),
+        PageEntry::Level(LevelId::ConveyorAlley),
+    ],
+    &[
+        PageEntry::Level(LevelId::EasyBox),
+        PageEntry::Level(LevelId::PushingMyButtons),
+        PageEntry::Level(LevelId::AcidRiver),
+        PageEntry::Level(LevelId::BoxMaze),
+        PageEntry::Level(LevelId::ConveyorLoop),
+    ],
+    &[
+        PageEntry::Level(LevelId::CatCoordination),
+        PageEntry::Level(LevelId::HelpMeOut),
+        PageEntry::Level(LevelId::ParkingLot),
+        PageEntry::Level(LevelId::PushingMyBoxes),
+        PageEntry::Level(LevelId::OneWayDoor),
+    ],
+    &[
+        PageEntry::Level(LevelId::PlayingWithFire),
+        PageEntry::Level(LevelId::ClosetFire),
+        PageEntry::Level(LevelId::ExtinguishStrategy),
+    ],
+    &[
+        PageEntry::Level(LevelId::Teleportation),
+        PageEntry::Level(LevelId::Glitch),
+        PageEntry::Level(LevelId::BlockedPortals),
+        PageEntry::Level(LevelId::KittyArmy),
+        PageEntry::Level(LevelId::MassExtinguish),
+    ],
+    &[
+        PageEntry::Editor,
+        PageEntry::WatchReplay,
+        PageEntry::Credits,
+        PageEntry::Links,
+    ],
+];
+pub const PAGE_NAMES: &[&str] = &[
+    "Tutorial",
+    "Pushing boxes",
+    "Two Cat Conundrum",
+    "Factory Emergency",
+    "Portal 3",
+    "Extras",
+];
@@ -0,0 +1,673 @@
+use turbo::{time::tick, *};
+
+use crate::{
+    levels::WinState,
+    menu::button_held,
+    object::{Object, ObjectInfo},
+    robot::Roam,
+    util::{Direction, Point},
+    world::{World, WorldEvent},
+};
+
+/// Play the sounds queued up by the world since the last frame
+pub fn play_events(world: &mut World) {
+    for event in world.events.drain(..) {
+        if let WorldEvent::Sound(sound) = event {
+            audio::play(sound.name());
+        }
+    }
+}
+
+/// Channels the editor offers, and the color each is drawn in
+pub const CHANNELS: [(&str, u32); 6] = [
+    ("red", 0xBC4040FF),
+    ("green", 0x3FB84AFF),
+    ("blue", 0x5199D7FF),
+    ("yellow", 0xCBB41CFF),
+    ("purple", 0x874ED6FF),
+    ("orange", 0xD07435FF),
+];
+
+/// Color a channel is drawn in. Channels the editor doesn't offer get a color from their name.
+pub fn channel_color(name: &str) -> u32 {
+    match CHANNELS.iter().find(|(channel, _)| *channel == name) {
+        Some((_, color)) => *color,
+        None => {
+            let hash = name
+                .bytes()
+                .fold(0usize, |a, b| a.wrapping_mul(31).wrapping_add(b as usize));
+            CHANNELS[hash % CHANNELS.len()].1
+        }
+    }
+}
+
+/// Screen position of the middle of a cell's floor
+fn cell_center(point: Point) -> (i32, i32) {
+    let pos = World::to_screen_space(point);
+    (pos.0 + 26, pos.1 + 14)
+}
+
+/// Draw a mirror across the floor of the cell with its top left at (x, y)
+fn draw_mirror(x: i32, y: i32, slash: bool) {
+    let (start, end) = if slash {
+        ((x + 7, y + 24), (x + 45, y + 4))
+    } else {
+        ((x + 17, y + 4), (x + 35, y + 24))
+    };
+    path!(start = start, end = end, size = 3, color = 0xC8DCE8FF);
+    path!(
+        start = (start.0, start.1 - 3),
+        end = (end.0, end.1 - 3),
+        size = 1,
+        color = 0xFFFFFFFF
+    );
+}
+
+/// Draw a key with the top left of its ring at (x, y)
+fn draw_key(x: i32, y: i32, color: u32, fixed: bool) {
+    circ!(
+        d = 7,
+        x = x,
+        y = y,
+        color = 0x00000000,
+        border_size = 2,
+        border_color = color,
+        fixed = fixed
+    );
+    rect!(
+        x = x + 6,
+        y = y + 3,
+        w = 8,
+        h = 2,
+        color = color,
+        fixed = fixed
+    );
+    rect!(
+        x = x + 11,
+        y = y + 5,
+        w = 2,
+        h = 3,
+        color = color,
+        fixed = fixed
+    );
+}
+
+/// Draw a labelled box for a logic object, filled in while its output is on
+fn draw_chip(label: &str, x: i32, y: i32, color: u32, on: bool) {
+    rect!(
+        x = x + 12,
+        y = y + 7,
+        w = 26,
+        h = 13,
+        color = if on { color } else { 0x333333FF },
+        border_radius = 2,
+        border_size = 1,
+        border_color = color,
+    );
+    text!(label, x = x + 14, y = y + 10, color = 0xFFFFFFFF);
+}
+
+/// Draw a row of three stars with the top left at (x, y), filling in the first `stars` of them
+pub fn draw_stars(stars: usize, x: i32, y: i32) {
+    for i in 0..3 {
+        let color = if i < stars { 0xF2C12EFF } else { 0x555555FF };
+        let center = (x + 5 + i as i32 * 12, y + 5);
+        // Join every second corner of a pentagon to make a five pointed star
+        let corners: Vec<(i32, i32)> = (0..5)
+            .map(|k| {
+                let angle =
+                    (k * 2 % 5) as f64 * std::f64::consts::TAU / 5.0 - std::f64::consts::FRAC_PI_2;
+                (
+                    center.0 + (angle.cos() * 5.0).round() as i32,
+                    center.1 + (angle.sin() * 5.0).round() as i32,
+                )
+            })
+            .collect();
+        for k in 0..5 {
+            path!(
+                start = corners[k],
+                end = corners[(k + 1) % 5],
+                size = 1,
+                color = color,
+                fixed = true
+            );
+        }
+    }
+}
+
+impl World {
+    /// Draw the whole world, with the wiring overlay on top if asked for and the level allows it
+    pub fn draw(&mut self, show_wiring: bool) {
+        self.draw_cells();
+        if show_wiring && !self.hide_wiring {
+            self.draw_wiring();
+        }
+        self.draw_hud();
+    }
+    /// Draw a wire from each button to the cell it powers, lit while that wire is on
+    pub fn draw_wiring(&self) {
+        for point in self.cells_iterator() {
+            for obj in self[point].iter() {
+                let (ObjectInfo::PushButton(target, idx)
+                | ObjectInfo::ToggleButton(target, idx)
+                | ObjectInfo::LaserReceiver(target, idx)) = obj.obj_type
+                else {
+                    continue;
+                };
+                if !self.point_inside(target) {
+                    continue;
+                }
+                let wire = (target.x() + target.y() * self.width as i32) as usize;
+                let color = if self.wiring[wire][idx] {
+                    0xCBB41CFF
+                } else {
+                    0x555555CC
+                };
+                let end = cell_center(target);
+                path!(
+                    start = cell_center(point),
+                    end = end,
+                    size = 2,
+                    color = color
+                );
+                circ!(d = 6, x = end.0 - 3, y = end.1 - 3, color = color);
+            }
+        }
+    }
+    /// Draw the floors and objects of the world
+    pub fn draw_cells(&mut self) {
+        // Draw floors
+        for y in 0..self.height {
+            for x in (0..self.width).rev() {
+                if !self[(x, y).into()]
+                    .iter()
+                    .any(|v| v.obj_type == ObjectInfo::Barrier)
+                {
+                    let pos = World::to_screen_space((x, y).into());
+                    sprite!(
+                        ["factory/floor3", "factory/floor3_1"][(x + y) % 2],
+                        x = pos.0,
+                        y = pos.1
+                    );
+                }
+            }
+        }
+        self.draw_active_cat();
+        // Tuples of (location, index, z-index)
+        let mut draw_array: Vec<(Point, usize, i32)> = vec![];
+        // Iterate over all grid cells and add to sprite list
+        for y in 0..self.height {
+            for x in (0..self.width).rev() {
+                let pos = (x, y).into();
+                for i in 0..self[pos].len() {
+                    let world_pos = self[pos][i].draw_pos;
+                    let z_index = self[pos][i].draw_height() + world_pos.1.get(tick()) * 50
+                        - world_pos.0.get(tick()) * 25;
+                    draw_array.push((pos, i, z_index));
+                }
+            }
+        }
+        // Sort sprite list by z-index
+        draw_array.sort_by_key(|v| v.2);
+        // Draw items in sprite array
+        for (position, index, _) in draw_array {
+            self[position][index].draw();
+        }
+        self.draw_beams();
+        self.draw_fire_warnings();
+    }
+    /// Flash the cells that fire will spread to at the end of the next move
+    fn draw_fire_warnings(&self) {
+        if !self.fire_spreads_next_move() || self.win_state != WinState::Alive {
+            return;
+        }
+        let opacity = if tick() % 40 < 20 { 0xFF } else { 0x88 };
+        for point in self.next_fires() {
+            let (x, y) = cell_center(point);
+            circ!(
+                d = 14,
+                x = x - 7,
+                y = y - 7,
+                color = 0x00000000,
+                border_size = 2,
+                border_color = 0xd0743500 | opacity
+            );
+            text!("!", x = x - 1, y = y - 4, color = 0xd0743500 | opacity);
+        }
+    }
+    /// Draw the beam of every laser over the objects it passes
+    fn draw_beams(&self) {
+        for beam in self.beams() {
+            let mut start = cell_center(beam.start);
+            for point in beam.cells {
+                let end = cell_center(point);
+                path!(start = start, end = end, size = 2, color = 0xFF3030CC);
+                start = end;
+            }
+        }
+    }
+    /// Ring the floor under the cat that moves, in levels where only one cat moves at a time
+    fn draw_active_cat(&self) {
+        let Some(id) = self.active_cat() else {
+            return;
+        };
+        let Some(cat) = self.inner.iter().flatten().find(|v| v.id == id) else {
+            return;
+        };
+        let x = cat.draw_pos.0.get(tick());
+        let y = cat.draw_pos.1.get(tick());
+        rect!(
+            x = x + 8,
+            y = y + 4,
+            w = 36,
+            h = 20,
+            color = 0x00000000,
+            border_radius = 10,
+            border_size = 2,
+            border_color = 0xCBB41CFF,
+        );
+    }
+    /// Draw the move counter, hint button, and caption
+    pub fn draw_hud(&mut self) {
+        // Draw move count text
+        let move_count = format!("Moves: {}", self.move_id);
+        text!(move_count.as_str(), x = 35, y = 6, fixed = true);
+        // Keys the cats are carrying
+        let keys_x = 41 + move_count.len() as i32 * 5;
+        for (i, (_, color)) in self.keys.iter().enumerate() {
+            draw_key(keys_x + i as i32 * 16, 6, *color, true);
+        }
+        if self.active_cat().is_some() {
+            text!("Tab to switch cats", x = 35, y = 16, fixed = true);
+        }
+        // Hint button
+        let button_bounds = Bounds::with_size(50, 20)
+            .anchor_right(&turbo::screen())
+            .anchor_top(&turbo::screen())
+            .translate_y(5)
+            .translate_x(-5);
+        if !self.hint.is_empty() && button_held("See hint", button_bounds, 0x888888FF, 0x777777FF) {
+            text_box!(
+                self.hint.as_str(),
+                bounds = button_bounds
+                    .left_of_self()
+                    .adjust_width(200)
+                    .translate_x(-205),
+                align = "right",
+                fixed = true,
+            );
+        }
+        // Draw caption
+        text_box!(
+            self.caption.as_str(),
+            bounds = Bounds::with_size(350, 40)
+                .anchor_center_x(&turbo::screen())
+                .anchor_bottom(&turbo::screen()),
+            align = "center",
+            fixed = true,
+        );
+        if self.conveyance != 0 {
+            sprite!(
+                "loading",
+                scale_x = 2.0,
+                scale_y = 2.0,
+                y = turbo::screen().bottom() - 20,
+                x = 5,
+                fixed = true
+            );
+        }
+    }
+    /// Draw the status of each win requirement along the top of the screen
+    pub fn draw_requirements(&self) {
+        let reqs = self.win_requirements();
+        for (i, (met, text)) in reqs.iter().enumerate() {
+            let color = if *met { 0x3fb84aff } else { 0xFFFFFFFF };
+            text!(
+                text.as_str(),
+                x = 120 + i * 120,
+                y = 6,
+                fixed = true,
+                color = color
+            );
+        }
+    }
+}
+
+impl Object {
+    pub fn draw_height(&self) -> i32 {
+        match self.obj_type {
+            ObjectInfo::Trap => -500,
+            ObjectInfo::Cat => 500,
+            ObjectInfo::Goal => 499,
+            ObjectInfo::Box => 500,
+            ObjectInfo::Barrier => 0,
+            ObjectInfo::WallLeft(_) => 1500,
+            ObjectInfo::WallRight(_) => -2000,
+            ObjectInfo::WallBack(_) => -500,
+            ObjectInfo::WallFront => 2000,
+            ObjectInfo::PushButton(..) => -2000,
+            ObjectInfo::ToggleButton(..) => -2000,
+            ObjectInfo::Door(..) => 510,
+            ObjectInfo::Death => -500,
+            ObjectInfo::ToggleableConveyor(..) => -500,
+            ObjectInfo::RotateableConveyor(..) => -500,
+            ObjectInfo::Water => 500,
+            ObjectInfo::Fire => -500,
+            ObjectInfo::BurntBox => 500,
+            ObjectInfo::FireOut => -500,
+            ObjectInfo::Portal(..) => -500,
+            ObjectInfo::PushSwitch(_) | ObjectInfo::ToggleSwitch(_) => -2000,
+            ObjectInfo::Gate(..)
+            | ObjectInfo::Delay(..)
+            | ObjectInfo::Latch(..)
+            | ObjectInfo::Relay(..) => -2000,
+            ObjectInfo::Laser(_)
+            | ObjectInfo::Mirror(_)
+            | ObjectInfo::RotateableMirror(..)
+            | ObjectInfo::LaserReceiver(..) => 500,
+            ObjectInfo::Ice => -3000,
+            ObjectInfo::Key(_) => 499,
+            ObjectInfo::LockedDoor(..) => 510,
+            ObjectInfo::OneWay(_) => -400,
+            ObjectInfo::Pit(_) => -500,
+            ObjectInfo::Robot(..) => 500,
+        }
+    }
+    pub fn draw(&mut self) {
+        let x = self.draw_pos.0.get(tick());
+        let y = self.draw_pos.1.get(tick());
+        let anim = self.animation.get(tick());
+        match self.obj_type {
+            // OBJECTS
+            ObjectInfo::Box => sprite!("box", x = x - 1, y = y - 11),
+            ObjectInfo::Cat => {
+                sprite!(
+                    "house/cat",
+                    x = x + 5,
+                    y = y - 10,
+                    opacity = (1.0 - anim as f32 / 8.0).max(0.0)
+                );
+            }
+            ObjectInfo::Robot(roam, dir) => {
+                let light = match roam {
+                    Roam::Patrol => 0xE0C050FF,
+                    Roam::Bounce => 0x5199D7FF,
+                    Roam::Chase => 0xE04040FF,
+                };
+                let (dx, dy) = match dir {
+                    Direction::North => (4, -4),
+                    Direction::South => (-4, 4),
+                    Direction::East => (6, 2),
+                    Direction::West => (-6, -2),
+                };
+                circ!(
+                    d = 26,
+                    x = x + 13,
+                    y = y - 2,
+                    color = 0x505860FF,
+                    border_size = 2,
+                    border_color = 0x2A2E33FF
+                );
+                circ!(d = 6, x = x + 23 + dx, y = y + 8 + dy, color = light);
+            }
+            ObjectInfo::Goal => {
+                if anim == 0 {
+                    sprite!("goal", x = x, y = y - 16)
+                } else if anim == 1 || anim == 2 {
+                    sprite!("goal2", x = x, y = y - 16)
+                } else if anim == 3 || anim == 4 {
+                    sprite!("goal3", x = x, y = y - 16)
+                } else if anim == 5 || anim == 6 {
+                    sprite!("goal4", x = x, y = y - 16)
+                } else if anim == 7 || anim == 8 {
+                    sprite!("goal5", x = x, y = y - 16)
+                } else {
+                    sprite!("goal6", x = x, y = y - 16 - (anim - 8) * 12)
+                }
+            }
+            // TRAPS
+            ObjectInfo::Trap => sprite!("trap2", x = x, y = y,),
+            ObjectInfo::Death => {
+                if tick() % 30 < 15 {
+                    sprite!("factory/acid", x = x, y = y)
+                } else {
+                    sprite!("factory/acid_1", x = x, y = y)
+                }
+            }
+            ObjectInfo::Pit(false) => rect!(
+                x = x + 6,
+                y = y + 3,
+                w = 40,
+                h = 22,
+                color = 0x141414FF,
+                border_radius = 6,
+                border_size = 2,
+                border_color = 0x3A3A3AFF,
+            ),
+            ObjectInfo::Pit(true) => rect!(
+                x = x + 6,
+                y = y + 3,
+                w = 40,
+                h = 22,
+                color = 0x8A6A40FF,
+                border_radius = 6,
+                border_size = 2,
+                border_color = 0x5E4528FF,
+            ),
+            ObjectInfo::Fire => {
+                sprite!(
+                    "factory/fire",
+                    frame = ((tick() as f32 + y as f32 * 10.0 + x as f32 * 5.0 + x as f32 % 51.0)
+                        / 10.0) as usize,
+                    x = x + 5,
+                    y = y - 25
+                )
+            }
+            ObjectInfo::BurntBox => {
+                sprite!(
+                    "factory/smoke",
+                    x = World::to_screen_space(self.position).0,
+                    y = World::to_screen_space(self.position).1 - anim * 5,
+                    opacity = 1.0 - anim as f32 / 10.0
+                )
+            }
+            ObjectInfo::FireOut => {
+                sprite!("factory/charcoal", x = x, y = y)
+            }
+            ObjectInfo::Water => {
+                sprite!("factory/water_bucket", x = x, y = y - 10)
+            }
+
+            // WALLS
+            ObjectInfo::Barrier => {}
+            ObjectInfo::WallBack(true) => sprite!("factory/front_wall", x = x, y = y - 3),
+            ObjectInfo::WallBack(false) => sprite!("factory/back_wall2", x = x, y = (y - 32)),
+            ObjectInfo::WallFront => sprite!("factory/front_wall", x = x + 14, y = y + 25),
+            ObjectInfo::WallLeft(true) => sprite!("factory/left_wall", x = x, y = y - 32),
+            ObjectInfo::WallLeft(false) => sprite!("factory/right_wall", x = x, y = y - 27),
+            ObjectInfo::WallRight(true) => {
+                sprite!("factory/right_wall_short", x = x + 37, y = y - 34)
+            }
+            ObjectInfo::WallRight(false) => sprite!("factory/right_wall", x = x + 38, y = y - 27),
+
+            // BUTTONS
+            ObjectInfo::PushButton(..) => {
+                if anim == 0 {
+                    sprite!("house/push_button_open", x = x, y = y)
+                } else {
+                    sprite!("house/push_button", x = x, y = y)
+                }
+            }
+            ObjectInfo::ToggleButton(..) => {
+                if anim == 0 {
+                    sprite!("house/toggle_button_open", x = x, y = y)
+                } else {
+                    sprite!("house/toggle_button", x = x, y = y)
+                }
+            }
+
+            // DOORS
+            ObjectInfo::Door(Direction::South | Direction::North, _) => {
+                if anim == 0 {
+                    sprite!("factory/door_vertical_closed", x = x + 19, y = y - 17)
+                } else if anim == 1 {
+                    sprite!("factory/door_vertical_middle", x = x + 19, y = y - 17)
+                } else if anim == 2 {
+                    sprite!("factory/door_vertical_open", x = x + 19, y = y - 17)
+                }
+            }
+            ObjectInfo::Door(Direction::East | Direction::West, _) => {
+                if anim == 0 {
+                    sprite!("factory/door_horizontal_closed", x = x + 6, y = y - 7)
+                } else if anim == 1 {
+                    sprite!("factory/door_horizontal_middle", x = x + 6, y = y - 7)
+                } else if anim == 2 {
+                    sprite!("factory/door_horizontal_open", x = x + 6, y = y - 7)
+                }
+            }
+            // CONVEYOR BELTS
+            ObjectInfo::RotateableConveyor(dir, phant, false)
+            | ObjectInfo::RotateableConveyor(phant, dir, true) => {
+                match dir {
+                    Direction::North => sprite!("factory/conveyor_up", x = x, y = y),
+                    Direction::South => sprite!("factory/conveyor_down", x = x, y = y),
+                    Direction::East => sprite!("factory/conveyor_right", x = x, y = y),
+                    Direction::West => sprite!("factory/conveyor_left", x = x, y = y),
+                }
+                match phant {
+                    Direction::North => sprite!("factory/phantom_up", x = x, y = y),
+                    Direction::South => sprite!("factory/phantom_down", x = x, y = y),
+                    Direction::East => sprite!("factory/phantom_right", x = x, y = y),
+                    Direction::West => sprite!("factory/phantom_left", x = x, y = y),
+                }
+            }
+            ObjectInfo::ToggleableConveyor(phant, false) => {
+                sprite!("factory/conveyor_empty", x = x, y = y);
+                match phant {
+                    Direction::North => sprite!("factory/phantom_up", x = x, y = y),
+                    Direction::South => sprite!("factory/phantom_down", x = x, y = y),
+                    Direction::East => sprite!("factory/phantom_right", x = x, y = y),
+                    Direction::West => sprite!("factory/phantom_left", x = x, y = y),
+                }
+            }
+            ObjectInfo::ToggleableConveyor(dir, true) => match dir {
+                Direction::North => sprite!("factory/conveyor_up", x = x, y = y),
+                Direction::South => sprite!("factory/conveyor_down", x = x, y = y),
+                Direction::East => sprite!("factory/conveyor_right", x = x, y = y),
+                Direction::West => sprite!("factory/conveyor_left", x = x, y = y),
+            },
+
+            //PORTALS
+            ObjectInfo::Portal(_, false, _) => sprite!("factory/portal_closed", x = x, y = y),
+            ObjectInfo::Portal(_, true, color) => {
+                sprite!("factory/portal_open", color = color, x = x, y = y)
+            }
+
+            // SIGNALS
+            ObjectInfo::PushSwitch(ref channel) => {
+                let color = channel_color(channel);
+                if anim == 0 {
+                    sprite!("house/push_button_open", color = color, x = x, y = y)
+                } else {
+                    sprite!("house/push_button", color = color, x = x, y = y)
+                }
+            }
+            ObjectInfo::ToggleSwitch(ref channel) => {
+                let color = channel_color(channel);
+                if anim == 0 {
+                    sprite!("house/toggle_button_open", color = color, x = x, y = y)
+                } else {
+                    sprite!("house/toggle_button", color = color, x = x, y = y)
+                }
+            }
+            ObjectInfo::Gate(gate, _, ref output) => draw_chip(
+                &gate.name().to_uppercase(),
+                x,
+                y,
+                channel_color(output),
+                anim != 0,
+            ),
+            ObjectInfo::Delay(_, ref output, ..) => {
+                draw_chip("DLY", x, y, channel_color(output), anim != 0)
+            }
+            ObjectInfo::Latch(_, _, ref output, _) => {
+                draw_chip("LAT", x, y, channel_color(output), anim != 0)
+            }
+            ObjectInfo::Relay(ref channel, _) => {
+                draw_chip("RLY", x, y, channel_color(channel), anim != 0)
+            }
+
+            // LASERS
+            ObjectInfo::Laser(dir) => {
+                let label = match dir {
+                    Direction::North => "^",
+                    Direction::South => "v",
+                    Direction::East => ">",
+                    Direction::West => "<",
+                };
+                draw_chip(label, x, y, 0xFF3030FF, true)
+            }
+            ObjectInfo::Mirror(slash) => draw_mirror(x, y, slash),
+            ObjectInfo::RotateableMirror(slash, on) => draw_mirror(x, y, slash != on),
+            ObjectInfo::LaserReceiver(..) => draw_chip("RCV", x, y, 0xFF3030FF, anim != 0),
+
+            // KEYS
+            ObjectInfo::Key(color) => draw_key(x + 19, y + 8, color, false),
+            ObjectInfo::LockedDoor(Direction::South | Direction::North, color) => {
+                sprite!(
+                    "factory/door_vertical_closed",
+                    color = color,
+                    x = x + 19,
+                    y = y - 17
+                )
+            }
+            ObjectInfo::LockedDoor(Direction::East | Direction::West, color) => {
+                sprite!(
+                    "factory/door_horizontal_closed",
+                    color = color,
+                    x = x + 6,
+                    y = y - 7
+                )
+            }
+
+            // ONE-WAY GATES
+            ObjectInfo::OneWay(dir) => {
+                rect!(
+                    x = x + 8,
+                    y = y + 3,
+                    w = 36,
+                    h = 22,
+                    color = 0x00000000,
+                    border_radius = 3,
+                    border_size = 1,
+                    border_color = 0xE0C050FF,
+                );
+                match dir {
+                    Direction::North => {
+                        sprite!("factory/phantom_up", color = 0xE0C050FF, x = x, y = y)
+                    }
+                    Direction::South => {
+                        sprite!("factory/phantom_down", color = 0xE0C050FF, x = x, y = y)
+                    }
+                    Direction::East => {
+                        sprite!("factory/phantom_right", color = 0xE0C050FF, x = x, y = y)
+                    }
+                    Direction::West => {
+                        sprite!("factory/phantom_left", color = 0xE0C050FF, x = x, y = y)
+                    }
+                }
+            }
+
+            // FLOORS
+            ObjectInfo::Ice => {
+                sprite!("factory/floor3", color = 0xA8E0FFFF, x = x, y = y);
+                path!(
+                    start = (x + 18, y + 18),
+                    end = (x + 30, y + 8),
+                    size = 1,
+                    color = 0xFFFFFFAA
+                );
+            }
+        }
+    }
+}
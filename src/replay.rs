@@ -0,0 +1,271 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    levels::{LevelBuilder, WinState},
+    menu::button,
+    registry::LevelId,
+    util::Direction,
+    world::World,
+};
+use turbo::*;
+
+/// Number of frames to wait between inputs at each playback speed
+const SPEEDS: &[usize] = &[60, 30, 15, 8, 4, 1];
+/// Speed playback starts at
+const DEFAULT_SPEED: usize = 2;
+/// Most inputs a parsed replay may expand to, so a pasted count can't exhaust memory
+const MAX_REPLAY_INPUTS: usize = 100_000;
+
+/// Something the player did to a world
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
+pub enum ReplayInput {
+    Move(Direction),
+    SwitchCat,
+    Undo,
+    Redo,
+    Reset,
+}
+
+impl From<ReplayInput> for char {
+    fn from(value: ReplayInput) -> Self {
+        match value {
+            ReplayInput::Move(dir) => char::from(dir),
+            ReplayInput::SwitchCat => 'S',
+            ReplayInput::Undo => 'Z',
+            ReplayInput::Redo => 'Y',
+            ReplayInput::Reset => 'X',
+        }
+    }
+}
+
+impl ReplayInput {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'S' => Some(ReplayInput::SwitchCat),
+            'Z' => Some(ReplayInput::Undo),
+            'Y' => Some(ReplayInput::Redo),
+            'X' => Some(ReplayInput::Reset),
+            'U' | 'D' | 'L' | 'R' => Direction::try_from(c).ok().map(ReplayInput::Move),
+            _ => None,
+        }
+    }
+}
+
+/// Every input applied to a level, in order.
+/// Written as the level name, a colon, then one character per input:
+/// U, D, L, and R for moves, S for switching cats, Z for undo, Y for redo, and X for reset.
+/// Runs of the same input are written as a count followed by the input, so `3L` is `LLL`.
+#[turbo::serialize]
+#[derive(PartialEq)]
+pub struct Replay {
+    pub level: LevelId,
+    pub inputs: Vec<ReplayInput>,
+}
+
+impl Replay {
+    pub fn new(level: LevelId) -> Self {
+        Self {
+            level,
+            inputs: vec![],
+        }
+    }
+    pub fn record(&mut self, input: ReplayInput) {
+        self.inputs.push(input);
+    }
+    /// Read a replay from the format written by `Display`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let Some((level, inputs)) = text.trim().rsplit_once(':') else {
+            return Err("Expected the level name followed by a colon".to_string());
+        };
+        let level = level.trim().parse().map_err(|err| format!("{}", err))?;
+        let mut out = Self::new(level);
+        let mut count = String::new();
+        for c in inputs.trim().chars() {
+            if c.is_ascii_digit() {
+                count.push(c);
+                continue;
+            }
+            let Some(input) = ReplayInput::from_char(c) else {
+                return Err(format!("Unknown input '{}'", c));
+            };
+            let repeat = if count.is_empty() {
+                1
+            } else {
+                count
+                    .parse::<usize>()
+                    .map_err(|_| "Replay is too long".to_string())?
+            };
+            count.clear();
+            if repeat > MAX_REPLAY_INPUTS - out.inputs.len() {
+                return Err("Replay is too long".to_string());
+            }
+            out.inputs.extend(std::iter::repeat_n(input, repeat));
+        }
+        if !count.is_empty() {
+            return Err(format!("Count {} is not followed by an input", count));
+        }
+        Ok(out)
+    }
+}
+
+impl Display for Replay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_fmt(format_args!("{}:", self.level))?;
+        let mut i = 0;
+        while i < self.inputs.len() {
+            let run = self.inputs[i..]
+                .iter()
+                .take_while(|v| **v == self.inputs[i])
+                .count();
+            // A count only saves space once the run is longer than two
+            if run > 2 {
+                f.write_fmt(format_args!("{}{}", run, char::from(self.inputs[i])))?;
+            } else {
+                for _ in 0..run {
+                    f.write_fmt(format_args!("{}", char::from(self.inputs[i])))?;
+                }
+            }
+            i += run;
+        }
+        Ok(())
+    }
+}
+
+/// Plays a replay back on a world, waiting for conveyors and animations between inputs
+#[turbo::serialize]
+pub struct Playback {
+    pub replay: Replay,
+    /// Index of the next input to apply
+    next: usize,
+    /// Index into `SPEEDS`
+    speed: usize,
+    /// Frames left before the next input
+    wait: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next: 0,
+            speed: DEFAULT_SPEED,
+            wait: SPEEDS[DEFAULT_SPEED],
+        }
+    }
+    /// World the replay starts from
+    pub fn start_world(&self) -> World {
+        LevelBuilder::get_template(self.replay.level)
+    }
+    pub fn done(&self) -> bool {
+        self.next >= self.replay.inputs.len()
+    }
+    /// Progress through the replay as (inputs applied, total inputs)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.replay.inputs.len())
+    }
+    pub fn speed(&self) -> usize {
+        self.speed + 1
+    }
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+        self.wait = self.wait.min(SPEEDS[self.speed]);
+    }
+    pub fn slower(&mut self) {
+        self.speed = self.speed.max(1) - 1;
+    }
+    /// Run one frame of the world, applying the next input once everything has stopped moving
+    pub fn update(&mut self, world: &mut World) {
+        if world.win_state == WinState::Alive {
+            world.convey();
+        }
+        self.wait = self.wait.max(1) - 1;
+        if world.conveyance == 0 && self.wait == 0 && !self.done() {
+            match self.replay.inputs[self.next] {
+                ReplayInput::Move(dir) => {
+                    if world.win_state == WinState::Alive {
+                        world.movement(dir);
+                    }
+                }
+                ReplayInput::SwitchCat => world.switch_cat(),
+                ReplayInput::Undo => world.undo(),
+                ReplayInput::Redo => world.redo(),
+                ReplayInput::Reset => *world = self.start_world(),
+            }
+            self.next += 1;
+            self.wait = SPEEDS[self.speed];
+        }
+        if world.conveyance == 1 {
+            world.convey();
+        }
+    }
+}
+
+/// Text box for typing or pasting in a shared replay
+#[turbo::serialize]
+pub struct ReplayImport {
+    text: String,
+    error: String,
+}
+
+impl ReplayImport {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            error: String::new(),
+        }
+    }
+    /// Draw the text box and handle typing.
+    /// Returns the replay and the (page, puzzle) of its level once a valid replay is entered.
+    pub fn run(&mut self) -> Option<(Replay, usize, usize)> {
+        let keyboard = keyboard::get();
+        if keyboard.backspace().just_pressed() {
+            self.text.pop();
+        }
+        self.text
+            .extend(keyboard.text().chars().filter(|c| !c.is_control()));
+        let bounds = Bounds::with_size(300, 80).anchor_center(&turbo::screen());
+        text_box!(
+            "Type or paste a replay, then press enter",
+            bounds = bounds.height(12).translate_y(-20),
+            align = "center",
+            fixed = true,
+        );
+        rect!(
+            bounds = bounds.expand(3),
+            color = 0x222222FF,
+            fixed = true,
+            border_radius = 2
+        );
+        text_box!(self.text.as_str(), bounds = bounds, fixed = true);
+        text_box!(
+            self.error.as_str(),
+            bounds = bounds.height(12).translate_y(88),
+            align = "center",
+            fixed = true,
+            color = 0xBC4040FF,
+        );
+        let watch_bounds = Bounds::with_size(50, 20)
+            .anchor_center(&turbo::screen())
+            .translate_y(70);
+        if !button("Watch", watch_bounds, 0x777777FF, 0x888888FF)
+            && !keyboard.enter().just_pressed()
+            || self.text.is_empty()
+        {
+            return None;
+        }
+        let replay = match Replay::parse(&self.text) {
+            Ok(replay) => replay,
+            Err(err) => {
+                self.error = err;
+                return None;
+            }
+        };
+        let Some((page_id, puzzle_id)) = replay.level.page_position() else {
+            self.error = format!("{} is not on the puzzle pages", replay.level);
+            return None;
+        };
+        self.error.clear();
+        Some((replay, page_id, puzzle_id))
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    object::{MoveType, ObjectId, ObjectInfo},
+    util::{Direction, Point},
+    world::World,
+};
+
+/// Rule a robot follows to pick its step after each move
+#[turbo::serialize]
+#[derive(Copy, PartialEq, Eq, Hash)]
+pub enum Roam {
+    /// Walks straight ahead, turning right whenever it is blocked, so it circles around obstacles
+    Patrol,
+    /// Walks straight ahead, turning around whenever it is blocked
+    Bounce,
+    /// Takes the first step of the shortest path to the nearest cat
+    Chase,
+}
+
+impl Roam {
+    pub const ALL: [Roam; 3] = [Roam::Patrol, Roam::Bounce, Roam::Chase];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Roam::Patrol => "Patrol",
+            Roam::Bounce => "Bounce",
+            Roam::Chase => "Chase",
+        }
+    }
+}
+
+impl World {
+    /// Let every robot take one step, in reading order of where they started.
+    /// Called at the end of each move.
+    pub fn move_robots(&mut self) {
+        let robots: Vec<(Point, ObjectId)> = self
+            .cells_iterator()
+            .flat_map(|point| {
+                self[point]
+                    .iter()
+                    .filter(|v| matches!(v.obj_type, ObjectInfo::Robot(..)))
+                    .map(move |v| (point, v.id))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        for (point, id) in robots {
+            if self.win_state.is_dead() {
+                return;
+            }
+            let Some(i) = self[point].iter().position(|v| v.id == id) else {
+                continue;
+            };
+            let robot = self[point][i].obj_type.clone();
+            let ObjectInfo::Robot(roam, heading) = robot else {
+                continue;
+            };
+            let step = match roam {
+                Roam::Patrol => [
+                    heading,
+                    heading.rotate_right(),
+                    heading.opposite(),
+                    heading.rotate_left(),
+                ]
+                .into_iter()
+                .find(|dir| self.robot_can_enter(point, &robot, *dir)),
+                Roam::Bounce => [heading, heading.opposite()]
+                    .into_iter()
+                    .find(|dir| self.robot_can_enter(point, &robot, *dir)),
+                Roam::Chase => self.chase(point, &robot),
+            };
+            let Some(dir) = step else {
+                continue;
+            };
+            if dir != heading {
+                self.change_obj_info(point, i, ObjectInfo::Robot(roam, dir));
+            }
+            let old_dst = self[point + dir].clone();
+            let old_src = self[point].clone();
+            self.move_to(point, i, dir);
+            self.update_cell(point + dir, &old_dst, dir);
+            self.update_cell(point, &old_src, dir);
+        }
+    }
+    /// Returns true if the robot at point can step in the direction dir.
+    /// Robots walk into cats but not onto anything they would have to push, or into hazards.
+    fn robot_can_enter(&self, point: Point, robot: &ObjectInfo, dir: Direction) -> bool {
+        let next = point + dir;
+        self.point_inside(next)
+            && !self.edge_blocked(point, dir)
+            && self[next].iter().all(|v| {
+                v.obj_type == ObjectInfo::Cat
+                    || (v.test_push_by(robot, dir) == MoveType::MoveOver
+                        && !matches!(
+                            v.obj_type,
+                            ObjectInfo::Death | ObjectInfo::Fire | ObjectInfo::Pit(false)
+                        ))
+            })
+    }
+    /// First step of the shortest path from the robot at start to a cat.
+    /// Ties go to the first direction in Direction::iter_all.
+    fn chase(&self, start: Point, robot: &ObjectInfo) -> Option<Direction> {
+        let mut seen = HashSet::from([start]);
+        let mut queue: VecDeque<(Point, Direction)> = Direction::iter_all()
+            .filter(|dir| self.robot_can_enter(start, robot, *dir) && seen.insert(start + *dir))
+            .map(|dir| (start + dir, dir))
+            .collect();
+        while let Some((point, first)) = queue.pop_front() {
+            if self[point].iter().any(|v| v.obj_type == ObjectInfo::Cat) {
+                return Some(first);
+            }
+            for dir in Direction::iter_all() {
+                if self.robot_can_enter(point, robot, dir) && seen.insert(point + dir) {
+                    queue.push_back((point + dir, first));
+                }
+            }
+        }
+        None
+    }
+}
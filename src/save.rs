@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use crate::{levels::WinState, replay::Replay, world::World};
+
+/// Version of the save format written by this build.
+/// Snapshots of levels in progress store the `World` layout, so this goes up whenever that layout changes.
+const SAVE_VERSION: u32 = 2;
+
+/// Levels that have been renamed, as (old name, new name), so progress follows them to the new name
+const RENAMED_LEVELS: &[(&str, &str)] = &[];
+
+/// Progress on a single level
+#[turbo::serialize]
+#[derive(Default)]
+pub struct LevelProgress {
+    pub solved: bool,
+    /// Fewest moves the level has been won in
+    pub best_moves: Option<usize>,
+    /// Encoded world and replay the player left the level in.
+    /// It is kept encoded so a snapshot from an older build can be dropped without losing the rest of the save.
+    in_progress: Option<Vec<u8>>,
+}
+
+/// Progress across every level, stored in local storage.
+/// Levels are keyed by their registry name rather than by position on the puzzle pages,
+/// so reordering `PUZZLE_PAGES` does not move progress to the wrong level.
+#[turbo::serialize]
+pub struct SaveData {
+    /// Always the first field so it can be read before decoding the rest
+    version: u32,
+    levels: BTreeMap<String, LevelProgress>,
+}
+
+impl SaveData {
+    pub fn new() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            levels: BTreeMap::new(),
+        }
+    }
+    /// Load the save data from local storage, starting fresh if there is none or it can't be read
+    pub fn load() -> Self {
+        let bytes = match turbo::local::load() {
+            Ok(bytes) if !bytes.is_empty() => bytes,
+            _ => return Self::new(),
+        };
+        match Self::from_bytes(&bytes) {
+            Ok(save) => save,
+            Err(err) => {
+                turbo::log!("Could not load save data: {}", err);
+                Self::new()
+            }
+        }
+    }
+    /// Write the save data to local storage
+    pub fn store(&self) {
+        if let Err(code) = turbo::local::save(&self.to_bytes()) {
+            turbo::log!("Could not write save data: error {}", code);
+        }
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        borsh::to_vec(self).expect("Save data always serializes")
+    }
+    /// Decode save data written by this or an older build
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let Some(version) = bytes.get(0..4) else {
+            return Err("save data is too short".to_string());
+        };
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        let save = match version {
+            1 | 2 => borsh::from_slice::<SaveData>(bytes).map_err(|err| err.to_string())?,
+            _ => return Err(format!("unknown save version {}", version)),
+        };
+        Ok(save.migrate())
+    }
+    /// Bring older save data up to date with this build
+    fn migrate(mut self) -> Self {
+        // Worlds have gained fields since version 1, so its snapshots can't be resumed
+        if self.version < 2 {
+            for level in self.levels.values_mut() {
+                level.in_progress = None;
+            }
+        }
+        for (old, new) in RENAMED_LEVELS {
+            if let Some(progress) = self.levels.remove(*old) {
+                self.levels.entry(new.to_string()).or_insert(progress);
+            }
+        }
+        self.version = SAVE_VERSION;
+        self
+    }
+    pub fn level(&self, name: &str) -> Option<&LevelProgress> {
+        self.levels.get(name)
+    }
+    pub fn solved(&self, name: &str) -> bool {
+        self.level(name).is_some_and(|level| level.solved)
+    }
+    /// Record a win of the level, clearing any world left in progress
+    pub fn record_win(&mut self, name: &str, moves: usize) {
+        let level = self.levels.entry(name.to_string()).or_default();
+        level.solved = true;
+        level.best_moves = Some(level.best_moves.map_or(moves, |best| best.min(moves)));
+        level.in_progress = None;
+    }
+    /// Remember the world the player is leaving the level in and how they got there.
+    /// Worlds that haven't been moved in or are already won are not worth resuming.
+    pub fn set_in_progress(&mut self, name: &str, world: &World, replay: &Replay) {
+        let snapshot = if world.move_id == 0 || world.win_state == WinState::Won {
+            None
+        } else {
+            Some(borsh::to_vec(&(world, replay)).expect("Worlds always serialize"))
+        };
+        if snapshot.is_none() && !self.levels.contains_key(name) {
+            return;
+        }
+        self.levels.entry(name.to_string()).or_default().in_progress = snapshot;
+    }
+    /// World and replay the player left the level in, if they can still be decoded
+    pub fn in_progress(&self, name: &str) -> Option<(World, Replay)> {
+        let bytes = self.level(name)?.in_progress.as_ref()?;
+        borsh::from_slice(bytes).ok()
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    object::ObjectInfo,
+    util::Point,
+    world::{Edit, World},
+};
+
+/// Most passes over the level while signals settle, so a loop of gates can't hang the game
+const MAX_SIGNAL_PASSES: usize = 32;
+
+/// Logic a gate applies to its input channels
+#[turbo::serialize]
+#[derive(Copy, PartialEq, Eq, Hash)]
+pub enum Gate {
+    And,
+    Or,
+    /// On when none of the inputs are on, so with one input it inverts it
+    Not,
+    /// On when an odd number of inputs are on
+    Xor,
+}
+
+impl Gate {
+    pub const ALL: [Gate; 4] = [Gate::And, Gate::Or, Gate::Not, Gate::Xor];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gate::And => "And",
+            Gate::Or => "Or",
+            Gate::Not => "Not",
+            Gate::Xor => "Xor",
+        }
+    }
+    pub fn apply(&self, mut inputs: impl Iterator<Item = bool>) -> bool {
+        match self {
+            Gate::And => inputs.all(|v| v),
+            Gate::Or => inputs.any(|v| v),
+            Gate::Not => !inputs.any(|v| v),
+            Gate::Xor => inputs.fold(false, |a, b| a ^ b),
+        }
+    }
+}
+
+impl World {
+    /// Returns true if the channel is on
+    pub fn channel(&self, name: &str) -> bool {
+        self.channels.contains(name)
+    }
+    /// Turn a channel on or off, returning true if it changed
+    pub fn set_channel(&mut self, name: &str, on: bool) -> bool {
+        if self.channel(name) == on {
+            return false;
+        }
+        self.edit_history
+            .push((self.move_id, Edit::Channel(name.to_string(), !on)));
+        self.store_channel(name, on);
+        true
+    }
+    /// Returns true if any push switch driving the channel is held down.
+    /// A channel with several push switches stays on until the last one is let go.
+    pub fn push_switch_held(&self, name: &str) -> bool {
+        self.cells_iterator().any(|point| {
+            self.covered(point)
+                && self[point].iter().any(
+                    |v| matches!(&v.obj_type, ObjectInfo::PushSwitch(channel) if channel == name),
+                )
+        })
+    }
+    /// Turn a channel on or off without logging it in history
+    pub fn store_channel(&mut self, name: &str, on: bool) {
+        if on {
+            self.channels.insert(name.to_string());
+        } else {
+            self.channels.remove(name);
+        }
+    }
+    /// Update gates, latches, delay outputs, and relays until every channel settles
+    pub fn propagate_signals(&mut self) {
+        for _ in 0..MAX_SIGNAL_PASSES {
+            let mut changed = false;
+            for point in self.cells_iterator() {
+                for i in 0..self[point].len() {
+                    changed |= self.update_signal(point, i);
+                }
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+    /// Shift every delay along by one move, then let the signals settle.
+    /// Called once at the end of each move.
+    pub fn tick_delays(&mut self) {
+        let mut changed = false;
+        for point in self.cells_iterator() {
+            for i in 0..self[point].len() {
+                if let ObjectInfo::Delay(input, output, last, on) = self[point][i].obj_type.clone()
+                {
+                    let new_last = self.channel(&input);
+                    if (new_last, last) != (last, on) {
+                        self.change_obj_info(
+                            point,
+                            i,
+                            ObjectInfo::Delay(input, output, new_last, last),
+                        );
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if changed {
+            self.propagate_signals();
+        }
+    }
+    /// Update one signal object from its inputs, returning true if anything changed
+    fn update_signal(&mut self, point: Point, i: usize) -> bool {
+        match self[point][i].obj_type.clone() {
+            ObjectInfo::Gate(gate, inputs, output) => {
+                let on = gate.apply(inputs.iter().map(|v| self.channel(v)));
+                self.drive(point, i, &output, on)
+            }
+            ObjectInfo::Delay(_, output, _, on) => self.drive(point, i, &output, on),
+            ObjectInfo::Latch(set, reset, output, on) => {
+                let new_on = if self.channel(&set) {
+                    true
+                } else if self.channel(&reset) {
+                    false
+                } else {
+                    on
+                };
+                if new_on != on {
+                    self.change_obj_info(
+                        point,
+                        i,
+                        ObjectInfo::Latch(set, reset, output.clone(), new_on),
+                    );
+                }
+                self.drive(point, i, &output, new_on) || new_on != on
+            }
+            ObjectInfo::Relay(channel, wire) => {
+                let on = self.channel(&channel);
+                self.show_signal(point, i, on);
+                self.set_wiring(point, wire, on)
+            }
+            _ => false,
+        }
+    }
+    /// Set the output channel of a signal object, returning true if it changed
+    fn drive(&mut self, point: Point, i: usize, output: &str, on: bool) -> bool {
+        self.show_signal(point, i, on);
+        self.set_channel(output, on)
+    }
+    /// Light up a signal object while its output is on
+    fn show_signal(&mut self, point: Point, i: usize, on: bool) {
+        if self[point][i].animation.end != on as i32 {
+            self.set_animation(point, i, on as i32, 1);
+        }
+    }
+    /// Replace an object's info and log the change in history
+    pub fn change_obj_info(&mut self, point: Point, i: usize, info: ObjectInfo) {
+        let old = std::mem::replace(&mut self[point][i].obj_type, info);
+        self.edit_history.push((
+            self.move_id,
+            Edit::ChangeObjInfo(point, self[point][i].id, old),
+        ));
+    }
+}
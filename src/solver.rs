@@ -0,0 +1,401 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::{Display, Formatter},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use crate::{
+    levels::WinState,
+    object::{ObjectId, ObjectInfo},
+    util::Direction,
+    world::World,
+};
+
+/// Number of distinct states the solver visits before giving up
+pub const DEFAULT_STATE_LIMIT: usize = 200_000;
+/// Number of states searched each frame when the game searches in the background, so it keeps running
+pub const STATES_PER_FRAME: usize = 100;
+/// Number of conveyor steps after a move before the conveyors are considered stuck in a loop
+const CONVEY_LIMIT: usize = 256;
+
+/// One input the solver can give a world
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
+pub enum Action {
+    Move(Direction),
+    /// Switch to the next cat, in levels where only one cat moves at a time
+    SwitchCat,
+}
+
+impl From<Action> for char {
+    fn from(value: Action) -> Self {
+        match value {
+            Action::Move(dir) => char::from(dir),
+            Action::SwitchCat => 'S',
+        }
+    }
+}
+
+/// Outcome of a search over a world
+#[turbo::serialize]
+#[derive(PartialEq)]
+pub enum Solution {
+    /// Inputs that win the level in the fewest moves. Switching cats doesn't count as a move.
+    Solved(Vec<Action>),
+    /// Every reachable state was visited and none of them win
+    Unsolvable,
+    /// The search visited this many states without finishing
+    LimitReached(usize),
+}
+
+impl Solution {
+    /// Number of moves in the solution, if there is one
+    pub fn move_count(&self) -> Option<usize> {
+        match self {
+            Solution::Solved(actions) => Some(
+                actions
+                    .iter()
+                    .filter(|v| matches!(v, Action::Move(_)))
+                    .count(),
+            ),
+            _ => None,
+        }
+    }
+    /// Solution as a string of U, D, L, and R characters, with S for switching cats
+    pub fn move_string(&self) -> Option<String> {
+        match self {
+            Solution::Solved(actions) => Some(actions.iter().map(|v| char::from(*v)).collect()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Solution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Solution::Solved(_) => f.write_fmt(format_args!(
+                "solvable in {} moves: {}",
+                self.move_count().unwrap_or_default(),
+                self.move_string().unwrap_or_default()
+            )),
+            Solution::Unsolvable => f.write_str("unsolvable"),
+            Solution::LimitReached(states) => {
+                f.write_fmt(format_args!("gave up after {} states", states))
+            }
+        }
+    }
+}
+
+/// Result of applying one input to a world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepResult {
+    /// Nothing changed
+    Unchanged,
+    /// Every win requirement was met at some point during the move
+    Won,
+    /// A cat died or the conveyors never came to rest
+    Lost,
+    /// The move was applied and the level is still being played
+    Alive,
+}
+
+/// Parts of a state besides its objects and wiring.
+/// Held keys are stored as (cell index, index in cell, color) because object IDs are not kept.
+/// The active cat is stored the same way, as (cell index, index in cell).
+/// The last entry is how far through its cycle spreading fire is.
+type Extras = (
+    BTreeSet<String>,
+    Vec<(usize, usize, u32)>,
+    Option<(usize, usize)>,
+    usize,
+);
+
+/// Position of an object as (cell index, index in cell)
+fn object_position(world: &World, id: ObjectId) -> Option<(usize, usize)> {
+    world.inner.iter().enumerate().find_map(|(cell_idx, cell)| {
+        let idx = cell.iter().position(|obj| obj.id == id)?;
+        Some((cell_idx, idx))
+    })
+}
+
+/// Write a number seven bits at a time, so small numbers take a single byte
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let (byte, rest) = bytes.split_first().expect("State keys always decode");
+        *bytes = rest;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// 128-bit hash of an encoded state. Visited states are stored as these rather than in full,
+/// which lets big levels be searched in a fraction of the memory; two states sharing one is
+/// vanishingly unlikely.
+fn fingerprint(key: &[u8]) -> u128 {
+    let halves = [0u8, 1].map(|seed| {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        key.hash(&mut hasher);
+        hasher.finish()
+    });
+    (halves[0] as u128) << 64 | halves[1] as u128
+}
+
+/// Numbers the object types seen during a search, so states can be stored as short lists of numbers
+#[derive(Debug, Clone, Default)]
+struct Palette {
+    infos: Vec<ObjectInfo>,
+    ids: HashMap<ObjectInfo, usize>,
+}
+
+impl Palette {
+    fn id(&mut self, info: &ObjectInfo) -> usize {
+        if let Some(id) = self.ids.get(info) {
+            return *id;
+        }
+        self.infos.push(info.clone());
+        self.ids.insert(info.clone(), self.infos.len() - 1);
+        self.infos.len() - 1
+    }
+    /// Encode everything that affects how a world plays.
+    /// Each cell is written as its number of objects then the number of each object,
+    /// followed by the wiring packed two cells to a byte, then the extras.
+    fn encode(&mut self, world: &World) -> Vec<u8> {
+        let mut out = vec![];
+        for cell in world.inner.iter() {
+            write_varint(&mut out, cell.len());
+            for obj in cell.iter() {
+                let id = self.id(&obj.obj_type);
+                write_varint(&mut out, id);
+            }
+        }
+        for pair in world.wiring.chunks(2) {
+            let mut byte = 0;
+            for (i, wire) in pair.iter().flatten().enumerate() {
+                byte |= (*wire as u8) << i;
+            }
+            out.push(byte);
+        }
+        let extras: Extras = (
+            world.channels.clone(),
+            world
+                .keys
+                .iter()
+                .filter_map(|(cat, color)| {
+                    let (cell_idx, idx) = object_position(world, *cat)?;
+                    Some((cell_idx, idx, *color))
+                })
+                .collect(),
+            world
+                .active_cat()
+                .and_then(|cat| object_position(world, cat)),
+            world.fire_spread.map_or(0, |moves| world.move_id % moves),
+        );
+        borsh::to_writer(&mut out, &extras).expect("State keys always serialize");
+        out
+    }
+    /// Rebuild a world from an encoded state, using the start world for everything the state leaves out
+    fn decode(&self, start: &World, mut bytes: &[u8], move_id: usize) -> World {
+        let mut world = start.clone();
+        world.move_id = move_id;
+        for point in start.cells_iterator() {
+            world[point].clear();
+            for _ in 0..read_varint(&mut bytes) {
+                let info = self.infos[read_varint(&mut bytes)].clone();
+                world.summon_object(point, info);
+            }
+        }
+        let (packed, rest) = bytes.split_at(world.wiring.len().div_ceil(2));
+        for (i, wires) in world.wiring.iter_mut().enumerate() {
+            for (j, wire) in wires.iter_mut().enumerate() {
+                *wire = (packed[i / 2] >> (i % 2 * 4 + j)) & 1 == 1;
+            }
+        }
+        let (channels, keys, active, _): Extras =
+            borsh::from_slice(rest).expect("State keys always deserialize");
+        world.channels = channels;
+        world.keys = keys
+            .into_iter()
+            .map(|(cell_idx, idx, color)| (world.inner[cell_idx][idx].id, color))
+            .collect();
+        world.keys.sort();
+        world.selected_cat = active.map(|(cell_idx, idx)| world.inner[cell_idx][idx].id);
+        world
+    }
+}
+
+/// Returns true if every win requirement of the world is satisfied
+pub fn requirements_met(world: &World) -> bool {
+    world.win_requirements().iter().all(|v| v.0)
+}
+
+/// Apply a move to the world the same way the game loop does, then run the conveyors until they stop.
+/// The edit history and events are cleared first, so this is meant for throwaway copies of a world.
+pub fn step(world: &mut World, dir: Direction) -> StepResult {
+    world.edit_history.clear();
+    world.events.clear();
+    let move_id = world.move_id;
+    world.movement(dir);
+    if world.move_id == move_id {
+        return StepResult::Unchanged;
+    }
+    // Conveyors that bring the world back to a state it was already in will carry it around forever
+    let mut seen = HashSet::new();
+    let mut palette = Palette::default();
+    // The game checks for a win every frame, so a cat passing through a goal on a conveyor counts
+    for _ in 0..CONVEY_LIMIT {
+        if requirements_met(world) {
+            return StepResult::Won;
+        }
+        if world.win_state.is_dead() {
+            return StepResult::Lost;
+        }
+        if world.conveyance == 0 {
+            return StepResult::Alive;
+        }
+        if !seen.insert(palette.encode(world)) {
+            return StepResult::Lost;
+        }
+        world.conveyance = 1;
+        world.convey();
+    }
+    StepResult::Lost
+}
+
+/// Find the shortest solution of a world with the default state limit
+pub fn solve(world: &World) -> Solution {
+    solve_with_limit(world, DEFAULT_STATE_LIMIT)
+}
+
+/// Breadth-first search over every move sequence from the given world
+pub fn solve_with_limit(world: &World, state_limit: usize) -> Solution {
+    let mut search = Search::new(world, state_limit);
+    loop {
+        if let Some(solution) = search.run(usize::MAX) {
+            return solution;
+        }
+    }
+}
+
+/// Breadth-first search that can be run a few states at a time, so the game can search between frames
+#[derive(Debug, Clone)]
+pub struct Search {
+    start: World,
+    state_limit: usize,
+    /// Tuples of (parent index, times the cat was switched, direction moved) taken from the parent
+    nodes: Vec<(usize, usize, Direction)>,
+    palette: Palette,
+    /// Fingerprints of visited states
+    visited: HashSet<u128>,
+    /// Queued states are stored encoded and rebuilt from the starting world when popped
+    queue: VecDeque<(Box<[u8]>, usize, usize)>,
+    /// Set once the search has finished
+    result: Option<Solution>,
+}
+
+impl Search {
+    pub fn new(world: &World, state_limit: usize) -> Self {
+        let mut start = world.clone();
+        start.edit_history.clear();
+        start.redo_history.clear();
+        start.events.clear();
+        start.win_state = WinState::Alive;
+        let mut palette = Palette::default();
+        let key: Box<[u8]> = palette.encode(&start).into();
+        let mut out = Self {
+            nodes: vec![(0, 0, Direction::North)],
+            palette,
+            visited: HashSet::from([fingerprint(&key)]),
+            queue: VecDeque::from([(key, 0, start.move_id)]),
+            start,
+            state_limit,
+            result: None,
+        };
+        if requirements_met(world) {
+            out.result = Some(Solution::Solved(vec![]));
+        }
+        out
+    }
+    /// Number of distinct states visited so far
+    pub fn visited(&self) -> usize {
+        self.visited.len()
+    }
+    /// Expand up to `budget` more states, returning the result once the search is finished
+    pub fn run(&mut self, budget: usize) -> Option<Solution> {
+        for _ in 0..budget {
+            if self.result.is_some() {
+                break;
+            }
+            let Some((key, node, move_id)) = self.queue.pop_front() else {
+                self.result = Some(Solution::Unsolvable);
+                break;
+            };
+            self.expand(&key, node, move_id);
+        }
+        self.result.clone()
+    }
+    fn expand(&mut self, key: &[u8], node: usize, move_id: usize) {
+        let current = self.palette.decode(&self.start, key, move_id);
+        // Switching is free, so moving any of the cats counts as one step of the search
+        let cats = match current.active_cat() {
+            Some(_) => current.cats().len(),
+            None => 1,
+        };
+        for switches in 0..cats {
+            let mut switched = current.clone();
+            for _ in 0..switches {
+                switched.switch_cat();
+            }
+            for dir in Direction::iter_all() {
+                let mut next = switched.clone();
+                let result = step(&mut next, dir);
+                if result == StepResult::Unchanged || result == StepResult::Lost {
+                    continue;
+                }
+                if result == StepResult::Won {
+                    self.nodes.push((node, switches, dir));
+                    self.result =
+                        Some(Solution::Solved(path_to(&self.nodes, self.nodes.len() - 1)));
+                    return;
+                }
+                let key: Box<[u8]> = self.palette.encode(&next).into();
+                if !self.visited.insert(fingerprint(&key)) {
+                    continue;
+                }
+                self.nodes.push((node, switches, dir));
+                if self.visited.len() >= self.state_limit {
+                    self.result = Some(Solution::LimitReached(self.visited.len()));
+                    return;
+                }
+                self.queue
+                    .push_back((key, self.nodes.len() - 1, next.move_id));
+            }
+        }
+    }
+}
+
+/// Walk back up the search tree to recover the inputs given to reach a node
+fn path_to(nodes: &[(usize, usize, Direction)], mut node: usize) -> Vec<Action> {
+    let mut path = vec![];
+    while node != 0 {
+        let (parent, switches, dir) = nodes[node];
+        path.push(Action::Move(dir));
+        path.extend(std::iter::repeat_n(Action::SwitchCat, switches));
+        node = parent;
+    }
+    path.reverse();
+    path
+}
@@ -0,0 +1,1150 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{
+    hint::{Hint, HintSearch},
+    level_file::{parse_level, serialize_level},
+    levels::{LevelBuilder, WinRequirement, WinState},
+    object::{ObjectId, ObjectInfo},
+    registry::{LevelId, LevelKind, PUZZLE_PAGES, PageEntry},
+    replay::{Replay, ReplayInput},
+    robot::Roam,
+    save::SaveData,
+    signal::Gate,
+    solver::{Solution, solve, solve_with_limit},
+    util::{Direction, Point},
+    validate::{Problem, validate},
+    world::World,
+};
+
+/// Known solutions for every level on the puzzle pages, as U, D, L, and R characters
+const SOLUTIONS: &[(LevelId, &str)] = &[
+    (LevelId::Movement, "ULLDLLL"),
+    (LevelId::Traps, "UULLDDLLUU"),
+    (LevelId::Buttons, "URULURDDDLLLLUUU"),
+    (LevelId::BoxBridge, "DLUDLLLURRDRULLLUU"),
+    (LevelId::ConveyorAlley, "UURLRDDDR"),
+    (LevelId::EasyBox, "ULDRDDLUURRR"),
+    (
+        LevelId::PushingMyButtons,
+        "RDDDRDLUURRRLLDLLLDRRRRLUULUURRR",
+    ),
+    (LevelId::AcidRiver, "DLDLUURRULLDLLUDDD"),
+    (
+        LevelId::BoxMaze,
+        "UUURUULDRDLLLRRUULLLDURRRDDDDDLLLLDLUUULURRLUU",
+    ),
+    (
+        LevelId::ConveyorLoop,
+        "RRDRRRUDLLLURDRRUUDLLURDRUDLLLLRRURR",
+    ),
+    (LevelId::CatCoordination, "DRRDDLULLUURDLDRUULRRRDRR"),
+    (LevelId::HelpMeOut, "URLDLURRUULLL"),
+    (LevelId::ParkingLot, "LURRDRUUULDDLDDR"),
+    (
+        LevelId::PushingMyBoxes,
+        "URDDRRULULDDDLDRDRURULDLULURRRUULLDDDRDDLL",
+    ),
+    (LevelId::OneWayDoor, "RLLUURDRDLLLDRDDUURRUURRRRUR"),
+    (
+        LevelId::PlayingWithFire,
+        "RRRRRRULDLUUULURRLLLLDDUURRDDDDLLLURRDRUURULULLDRRRURD",
+    ),
+    (LevelId::ClosetFire, "DDRDDLUUULURRRRLLDDDUULLDRRRLLUULL"),
+    (LevelId::ExtinguishStrategy, "RDLUUULDDRDDDUUDLLUUUURLLL"),
+    (LevelId::Teleportation, "DLURULLLLLLRRRRDDLL"),
+    (LevelId::Glitch, "RDLLLLLURRRUDULLLLL"),
+    (LevelId::BlockedPortals, "RDDLDRDLLLLRRRUUURRRRD"),
+    (LevelId::KittyArmy, "DUUUDRUULLLLLLLLRUURLLLLLR"),
+    (LevelId::MassExtinguish, "UDUULLDRLRLRLRLRLRLRLRLRRRRR"),
+];
+
+/// Everything about a world that undo is expected to restore
+type Snapshot = (
+    Vec<Vec<(ObjectId, ObjectInfo, Point, i32, (i32, i32))>>,
+    Vec<[bool; 4]>,
+    BTreeSet<String>,
+    Vec<(ObjectId, u32)>,
+    usize,
+);
+
+fn snapshot(world: &World) -> Snapshot {
+    (
+        world
+            .inner
+            .iter()
+            .map(|cell| {
+                cell.iter()
+                    .map(|obj| {
+                        (
+                            obj.id,
+                            obj.obj_type.clone(),
+                            obj.position,
+                            obj.animation.end,
+                            (obj.draw_pos.0.end, obj.draw_pos.1.end),
+                        )
+                    })
+                    .collect()
+            })
+            .collect(),
+        world.wiring.clone(),
+        world.channels.clone(),
+        world.keys.clone(),
+        world.move_id,
+    )
+}
+
+fn moves(solution: &str) -> Vec<Direction> {
+    solution
+        .chars()
+        .map(|c| Direction::try_from(c).expect("Solutions only contain U, D, L, and R"))
+        .collect()
+}
+
+/// Apply a move the same way the game loop does, running conveyors until they stop.
+/// Objects can ride a conveyor loop forever, so the conveyors are cut off after a while.
+fn play(world: &mut World, dir: Direction) {
+    world.movement(dir);
+    world.check_win();
+    for _ in 0..256 {
+        if world.conveyance == 0 || world.win_state != WinState::Alive {
+            break;
+        }
+        world.conveyance = 1;
+        world.convey();
+        world.check_win();
+    }
+}
+
+fn levels() -> impl Iterator<Item = LevelId> {
+    PUZZLE_PAGES
+        .iter()
+        .flat_map(|page| page.iter())
+        .filter_map(|entry| match entry {
+            PageEntry::Level(id) => Some(*id),
+            _ => None,
+        })
+}
+
+fn solution(name: LevelId) -> &'static str {
+    SOLUTIONS
+        .iter()
+        .find(|(level, _)| *level == name)
+        .unwrap_or_else(|| panic!("No solution stored for {}", name))
+        .1
+}
+
+/// Small deterministic generator so wandering tests are reproducible
+fn wander(seed: u64, len: usize) -> Vec<Direction> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            Direction::array_all()[(state >> 33) as usize % 4]
+        })
+        .collect()
+}
+
+/// Play the moves one at a time, then undo them one at a time checking every earlier state is restored,
+/// then redo them all checking every later state comes back.
+/// Stops early if a cat dies, since the game requires an undo before any further moves.
+fn check_undo(name: &str, world: &mut World, path: &[Direction]) {
+    let mut history = vec![snapshot(world)];
+    for dir in path {
+        let move_id = world.move_id;
+        play(world, *dir);
+        if world.move_id != move_id {
+            history.push(snapshot(world));
+        }
+        if world.win_state != WinState::Alive {
+            break;
+        }
+    }
+    let final_state = world.win_state;
+    for i in (0..history.len() - 1).rev() {
+        world.undo();
+        assert_eq!(
+            snapshot(world),
+            history[i],
+            "{}: undo to move {} did not restore the world",
+            name,
+            i
+        );
+        assert_eq!(
+            world.win_state,
+            WinState::Alive,
+            "{}: undo did not revive",
+            name
+        );
+    }
+    for (i, state) in history.iter().enumerate().skip(1) {
+        world.redo();
+        assert_eq!(
+            snapshot(world),
+            *state,
+            "{}: redo to move {} did not restore the world",
+            name,
+            i
+        );
+    }
+    world.check_win();
+    assert_eq!(
+        world.win_state, final_state,
+        "{}: redo changed the outcome",
+        name
+    );
+    for _ in 1..history.len() {
+        world.undo();
+    }
+}
+
+/// Play the moves on the level, calling expect with the number of moves played after each one,
+/// then check that undo and redo restore every move on a fresh copy of the level
+fn assert_plays(name: &str, level: impl Fn() -> World, path: &str, expect: impl Fn(usize, &World)) {
+    let mut world = level();
+    for (i, dir) in moves(path).into_iter().enumerate() {
+        play(&mut world, dir);
+        expect(i + 1, &world);
+    }
+    check_undo(name, &mut level(), &moves(path));
+}
+
+#[test]
+fn every_level_has_a_solution() {
+    for name in levels() {
+        solution(name);
+    }
+}
+
+#[test]
+fn solutions_meet_par() {
+    for name in levels() {
+        let world = LevelBuilder::get_template(name);
+        let par = world
+            .par
+            .unwrap_or_else(|| panic!("{}: level has no par", name));
+        let len = solution(name).len();
+        assert!(
+            len == par,
+            "{}: solution takes {} moves, par is {}",
+            name,
+            len,
+            par
+        );
+    }
+}
+
+/// States searched per level by `write_pars`, far more than the game searches for hints
+const PAR_STATE_LIMIT: usize = 100_000_000;
+
+/// Solves every level and writes the move counts to `src/pars.rs`.
+/// Run with `cargo test --release -- --ignored write_pars` after changing a level.
+#[test]
+#[ignore = "solves every level, which takes a long time; run with --release --ignored"]
+fn write_pars() {
+    let mut out = String::from(
+        "//! Fewest moves each puzzle can be won in, as found by the solver.\n\
+        //! Generated by `cargo test --release -- --ignored write_pars`; don't edit by hand.\n\n\
+        use crate::registry::LevelId;\n\n\
+        pub const PARS: &[(LevelId, usize)] = &[\n",
+    );
+    for name in levels() {
+        let solution = solve_with_limit(&LevelBuilder::get_template(name), PAR_STATE_LIMIT);
+        eprintln!("{}: {}", name, solution);
+        let count = solution
+            .move_count()
+            .unwrap_or_else(|| panic!("{}: no solution found", name));
+        out += &format!("    (LevelId::{:?}, {}),\n", name, count);
+    }
+    out += "];\n";
+    std::fs::write(concat!(env!("CARGO_MANIFEST_DIR"), "/src/pars.rs"), out).unwrap();
+}
+
+#[test]
+fn solutions_win() {
+    for name in levels() {
+        let mut world = LevelBuilder::get_template(name);
+        for (i, dir) in moves(solution(name)).into_iter().enumerate() {
+            assert_eq!(
+                world.win_state,
+                WinState::Alive,
+                "{}: not alive before move {}",
+                name,
+                i
+            );
+            play(&mut world, dir);
+        }
+        assert_eq!(
+            world.win_state,
+            WinState::Won,
+            "{}: solution did not win",
+            name
+        );
+    }
+}
+
+#[test]
+fn undo_restores_solutions() {
+    for name in levels() {
+        let mut world = LevelBuilder::get_template(name);
+        let start = snapshot(&world);
+        check_undo(name.name(), &mut world, &moves(solution(name)));
+        assert_eq!(snapshot(&world), start, "{}: not back at the start", name);
+    }
+}
+
+#[test]
+fn undo_restores_wandering() {
+    for (seed, name) in levels().enumerate() {
+        for run in 0..4 {
+            let mut world = LevelBuilder::get_template(name);
+            let start = snapshot(&world);
+            check_undo(name.name(), &mut world, &wander(seed as u64 * 4 + run, 40));
+            assert_eq!(snapshot(&world), start, "{}: not back at the start", name);
+        }
+    }
+}
+
+#[test]
+fn moving_clears_redo() {
+    for name in levels() {
+        let mut world = LevelBuilder::get_template(name);
+        let path = moves(solution(name));
+        play(&mut world, path[0]);
+        world.undo();
+        assert_eq!(
+            world.redo_history.len(),
+            1,
+            "{}: undo did not add a redo",
+            name
+        );
+        for dir in Direction::iter_all() {
+            play(&mut world, dir);
+            if world.move_id != 0 {
+                break;
+            }
+        }
+        assert_eq!(
+            world.redo_history.len(),
+            0,
+            "{}: moving kept the redo",
+            name
+        );
+    }
+}
+
+#[test]
+fn undo_at_start_does_nothing() {
+    for name in levels() {
+        let mut world = LevelBuilder::get_template(name);
+        let start = snapshot(&world);
+        world.undo();
+        assert_eq!(
+            snapshot(&world),
+            start,
+            "{}: undo changed a fresh level",
+            name
+        );
+    }
+}
+
+#[test]
+fn level_names_round_trip() {
+    for id in LevelId::ALL {
+        assert_eq!(id.name().parse::<LevelId>(), Ok(*id));
+    }
+    assert!("double_cat".parse::<LevelId>().is_err());
+}
+
+#[test]
+fn replays_round_trip_and_stay_short() {
+    let mut replay = Replay::new(LevelId::Movement);
+    for input in [
+        ReplayInput::Move(Direction::West),
+        ReplayInput::Move(Direction::West),
+        ReplayInput::Move(Direction::West),
+        ReplayInput::Undo,
+        ReplayInput::Redo,
+        ReplayInput::Reset,
+    ] {
+        replay.record(input);
+    }
+    assert_eq!(Replay::parse(&replay.to_string()), Ok(replay));
+    let too_long = Err("Replay is too long".to_string());
+    for inputs in ["100001U", "99999999999999999999999U", "60000U60000D"] {
+        let text = format!("{}:{}", LevelId::Movement, inputs);
+        assert_eq!(Replay::parse(&text), too_long, "{}", inputs);
+    }
+}
+
+#[test]
+fn old_saves_drop_snapshots() {
+    let name = LevelId::Movement.name();
+    let mut world = LevelBuilder::get_template(LevelId::Movement);
+    play(&mut world, Direction::North);
+    let mut save = SaveData::new();
+    save.record_win(LevelId::Traps.name(), 10);
+    save.set_in_progress(name, &world, &Replay::new(LevelId::Movement));
+    let bytes = save.to_bytes();
+    assert!(
+        SaveData::from_bytes(&bytes)
+            .unwrap()
+            .in_progress(name)
+            .is_some()
+    );
+    // Version 1 snapshots hold an older world layout
+    let mut old = bytes;
+    old[0..4].copy_from_slice(&1u32.to_le_bytes());
+    let loaded = SaveData::from_bytes(&old).unwrap();
+    assert!(loaded.in_progress(name).is_none());
+    assert!(loaded.solved(LevelId::Traps.name()));
+}
+
+#[test]
+fn only_puzzles_are_on_pages() {
+    for id in levels() {
+        assert!(
+            matches!(id.kind(), LevelKind::Puzzle(_)),
+            "{}: is on the puzzle pages",
+            id
+        );
+    }
+    for id in LevelId::ALL {
+        if let LevelKind::Puzzle(_) = id.kind() {
+            assert!(
+                id.page_position().is_some(),
+                "{}: is not on the puzzle pages",
+                id
+            );
+        }
+    }
+}
+
+#[test]
+fn levels_have_no_problems() {
+    for id in levels() {
+        let problems = validate(&LevelBuilder::get_template(id));
+        assert_eq!(problems, vec![], "{}: level has problems", id);
+    }
+}
+
+#[test]
+fn hints_for_solved_worlds_finish() {
+    let world = LevelBuilder::make_level(2, 1, &[&[true; 2]], WinRequirement::CatsInGoals(1))
+        .with_obj((0, 0), ObjectInfo::Goal)
+        .with_obj((0, 0), ObjectInfo::Cat)
+        .finish();
+    let mut hint = HintSearch::new(&world);
+    assert_eq!(hint.run(&world), Some(Hint::AlreadySolved));
+}
+
+#[test]
+fn validate_finds_problems() {
+    const T: bool = true;
+    const F: bool = false;
+    let world = LevelBuilder::make_level(
+        4,
+        3,
+        &[&[T, T, F, T], &[T, T, F, T], &[T, T, F, T]],
+        WinRequirement::CatsInGoals(2),
+    )
+    .with_obj((0, 0), ObjectInfo::Cat)
+    .with_obj((3, 0), ObjectInfo::Goal)
+    .with_obj((1, 0), ObjectInfo::PushButton((9, 9).into(), 0))
+    .with_obj((1, 1), ObjectInfo::ToggleButton((0, 2).into(), 0))
+    .with_obj((0, 1), ObjectInfo::Portal(vec![(1, 2).into()], true, 0))
+    .with_obj((2, 1), ObjectInfo::Box)
+    .finish();
+    assert_eq!(
+        validate(&world),
+        vec![
+            Problem::WireOutside((1, 0).into(), (9, 9).into()),
+            Problem::PortalEndClosed((0, 1).into(), (1, 2).into()),
+            Problem::WireToNothing((1, 1).into(), (0, 2).into()),
+            Problem::ObjectOnBarrier((2, 1).into()),
+            Problem::TooFewCats(2, 1),
+            Problem::TooFewGoals(2, 1),
+            Problem::UnreachableGoal((3, 0).into()),
+        ]
+    );
+}
+
+/// Number of objects of a type in a cell
+fn count(world: &World, point: (i32, i32), obj: ObjectInfo) -> usize {
+    world[point.into()]
+        .iter()
+        .filter(|v| v.obj_type == obj)
+        .count()
+}
+
+#[test]
+fn stacked_cats_move_together() {
+    let level = || {
+        let mut builder = LevelBuilder::make_level(4, 1, &[&[true; 4]], WinRequirement::Never);
+        for _ in 0..12 {
+            builder = builder.with_obj((0, 0), ObjectInfo::Cat);
+        }
+        builder.finish()
+    };
+    assert_plays("stacked cats", level, "R", |_, world| {
+        assert_eq!(count(world, (0, 0), ObjectInfo::Cat), 0);
+        assert_eq!(count(world, (1, 0), ObjectInfo::Cat), 12);
+    });
+}
+
+#[test]
+fn stacked_boxes_ride_conveyors() {
+    let level = || {
+        let mut builder = LevelBuilder::make_level(5, 1, &[&[true; 5]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj(
+                (2, 0),
+                ObjectInfo::ToggleableConveyor(Direction::East, true),
+            );
+        for _ in 0..9 {
+            builder = builder.with_obj((1, 0), ObjectInfo::Box);
+        }
+        builder.finish()
+    };
+    assert_plays("stacked boxes", level, "R", |_, world| {
+        assert_eq!(count(world, (1, 0), ObjectInfo::Cat), 1);
+        assert_eq!(count(world, (3, 0), ObjectInfo::Box), 9);
+    });
+}
+
+#[test]
+fn crowded_cell_presses_buttons() {
+    let level = || {
+        let mut builder = LevelBuilder::make_level(5, 1, &[&[true; 5]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((4, 0), ObjectInfo::Door(Direction::West, false))
+            .with_obj(
+                (2, 0),
+                ObjectInfo::ToggleableConveyor(Direction::East, false),
+            )
+            .with_obj((2, 0), ObjectInfo::Portal(vec![], false, 0));
+        for _ in 0..8 {
+            builder = builder.with_obj((2, 0), ObjectInfo::PushButton((4, 0).into(), 0));
+        }
+        builder.finish()
+    };
+    assert!(level()[(2, 0).into()].len() > 8);
+    assert_plays(
+        "crowded cell",
+        level,
+        "RRRL",
+        |played, world| match played {
+            3 => assert_eq!(count(world, (3, 0), ObjectInfo::Cat), 1),
+            4 => {
+                assert_eq!(count(world, (2, 0), ObjectInfo::Cat), 1);
+                let door = ObjectInfo::Door(Direction::West, true);
+                assert_eq!(count(world, (4, 0), door), 1);
+            }
+            _ => {}
+        },
+    );
+}
+
+#[test]
+fn solver_finds_shortest_solutions() {
+    let world = LevelBuilder::make_level(
+        3,
+        2,
+        &[&[true; 3], &[true; 3]],
+        WinRequirement::CatsInGoals(1),
+    )
+    .with_obj((0, 0), ObjectInfo::Cat)
+    .with_obj((2, 1), ObjectInfo::Goal)
+    .with_obj((1, 1), ObjectInfo::Box)
+    .finish();
+    // The box can't be pushed onto the goal, so the shortest route goes around it along the top
+    assert_eq!(solve(&world).move_string().as_deref(), Some("RRD"));
+    for (id, moves) in [
+        (LevelId::Movement, 7),
+        (LevelId::Traps, 10),
+        (LevelId::EasyBox, 12),
+    ] {
+        let world = LevelBuilder::get_template(id);
+        assert_eq!(solve(&world).move_count(), Some(moves), "{}", id);
+    }
+}
+
+#[test]
+fn solver_reports_unwinnable_levels() {
+    let world = LevelBuilder::make_level(
+        3,
+        1,
+        &[&[true, false, true]],
+        WinRequirement::CatsInGoals(1),
+    )
+    .with_obj((0, 0), ObjectInfo::Cat)
+    .with_obj((2, 0), ObjectInfo::Goal)
+    .finish();
+    assert_eq!(solve(&world), Solution::Unsolvable);
+}
+
+/// Returns true if no two objects in the world share an ID
+fn ids_unique(world: &World) -> bool {
+    let ids: Vec<ObjectId> = world.inner.iter().flatten().map(|obj| obj.id).collect();
+    let unique: HashSet<ObjectId> = ids.iter().copied().collect();
+    ids.len() == unique.len()
+}
+
+#[test]
+fn undo_restores_stacked_portal_copies() {
+    let level = || {
+        let mut builder = LevelBuilder::make_level(7, 1, &[&[true; 7]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Box)
+            .with_obj(
+                (1, 0),
+                ObjectInfo::Portal(vec![(3, 0).into(), (5, 0).into()], true, 0),
+            )
+            .with_obj((3, 0), ObjectInfo::Portal(vec![(1, 0).into()], true, 0))
+            .with_obj((5, 0), ObjectInfo::Portal(vec![(1, 0).into()], true, 0));
+        for _ in 0..3 {
+            builder = builder.with_obj((0, 0), ObjectInfo::Cat);
+        }
+        builder.finish()
+    };
+    assert_plays("stacked portal copies", level, "RRL", |played, world| {
+        assert!(ids_unique(world));
+        if played == 3 {
+            // Going through a portal with two ends copies the cats
+            let cats = world.inner.iter().flatten();
+            assert!(cats.filter(|v| v.obj_type == ObjectInfo::Cat).count() > 3);
+        }
+    });
+}
+
+/// Returns true if the door at the point is open
+fn door_open(world: &World, point: (i32, i32)) -> bool {
+    world[point.into()]
+        .iter()
+        .any(|v| matches!(v.obj_type, ObjectInfo::Door(_, true)))
+}
+
+#[test]
+fn gates_drive_relays() {
+    let level = || {
+        LevelBuilder::make_level(6, 1, &[&[true; 6]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 0), ObjectInfo::ToggleSwitch("a".to_string()))
+            .with_obj((2, 0), ObjectInfo::ToggleSwitch("b".to_string()))
+            .with_obj(
+                (3, 0),
+                ObjectInfo::Gate(
+                    Gate::And,
+                    vec!["a".to_string(), "b".to_string()],
+                    "and".to_string(),
+                ),
+            )
+            .with_obj(
+                (3, 0),
+                ObjectInfo::Gate(Gate::Not, vec!["and".to_string()], "nand".to_string()),
+            )
+            .with_obj((4, 0), ObjectInfo::Door(Direction::North, false))
+            .with_obj((4, 0), ObjectInfo::Relay("and".to_string(), 0))
+            .with_obj((5, 0), ObjectInfo::Door(Direction::North, false))
+            .with_obj((5, 0), ObjectInfo::Relay("nand".to_string(), 0))
+            .finish()
+    };
+    assert!(!door_open(&level(), (4, 0)));
+    assert!(door_open(&level(), (5, 0)));
+    assert_plays("gates", level, "RR", |played, world| {
+        // Both switches have to be on before the and gate opens its door
+        assert_eq!(door_open(world, (4, 0)), played == 2);
+        assert_eq!(door_open(world, (5, 0)), played != 2);
+    });
+}
+
+#[test]
+fn push_switches_share_channels() {
+    let mut world = LevelBuilder::make_level(6, 1, &[&[true; 6]], WinRequirement::Never)
+        .with_obj((0, 0), ObjectInfo::Cat)
+        .with_obj((1, 0), ObjectInfo::Box)
+        .with_obj((2, 0), ObjectInfo::PushSwitch("a".to_string()))
+        .with_obj((3, 0), ObjectInfo::PushSwitch("a".to_string()))
+        .finish();
+    // The channel stays on until the last switch is let go
+    for on in [true, true, true, false] {
+        play(&mut world, Direction::East);
+        assert_eq!(world.channel("a"), on, "after move {}", world.move_id);
+    }
+    check_undo("push switches", &mut world, &[Direction::West; 4]);
+}
+
+#[test]
+fn latches_and_delays_remember() {
+    let level = || {
+        LevelBuilder::make_level(5, 1, &[&[true; 5]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 0), ObjectInfo::PushSwitch("set".to_string()))
+            .with_obj((3, 0), ObjectInfo::PushSwitch("reset".to_string()))
+            .with_obj(
+                (4, 0),
+                ObjectInfo::Latch(
+                    "set".to_string(),
+                    "reset".to_string(),
+                    "latched".to_string(),
+                    false,
+                ),
+            )
+            .with_obj(
+                (4, 0),
+                ObjectInfo::Delay("set".to_string(), "delayed".to_string(), false, false),
+            )
+            .finish()
+    };
+    // (latched, delayed) after each move
+    let expected = [(true, false), (true, true), (false, false)];
+    assert_plays("latches", level, "RRR", |played, world| {
+        let seen = (world.channel("latched"), world.channel("delayed"));
+        assert_eq!(seen, expected[played - 1], "after move {}", played);
+    });
+}
+
+#[test]
+fn validate_finds_undriven_channels() {
+    let world = LevelBuilder::make_level(2, 1, &[&[true; 2]], WinRequirement::Never)
+        .with_obj(
+            (0, 0),
+            ObjectInfo::Gate(Gate::Or, vec!["in".to_string()], "out".to_string()),
+        )
+        .with_obj((1, 0), ObjectInfo::Door(Direction::North, false))
+        .with_obj((1, 0), ObjectInfo::Relay("out".to_string(), 0))
+        .finish();
+    assert_eq!(
+        validate(&world),
+        [Problem::UndrivenChannel("in".to_string())]
+    );
+}
+
+#[test]
+fn level_files_round_trip() {
+    let hidden = LevelBuilder::make_level(1, 1, &[&[true]], WinRequirement::Never)
+        .with_obj((0, 0), ObjectInfo::ToggleSwitch("red".to_string()))
+        .with_channel("red", true)
+        .with_hidden_wiring()
+        .finish();
+    // Gate inputs are counted, so a channel can share its name with the animation keyword
+    let anim = LevelBuilder::make_level(2, 1, &[&[true; 2]], WinRequirement::Never)
+        .with_obj((0, 0), ObjectInfo::PushSwitch("anim".to_string()))
+        .with_obj_anim(
+            (1, 0),
+            ObjectInfo::Gate(Gate::Or, vec!["anim".to_string()], "out".to_string()),
+            1,
+        )
+        .finish();
+    let worlds = LevelId::ALL
+        .iter()
+        .map(|id| (id.name(), LevelBuilder::get_template(*id)))
+        .chain([("hidden wiring", hidden), ("anim channel", anim)]);
+    for (name, world) in worlds {
+        let text = serialize_level(&world);
+        let parsed = parse_level(&text).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        assert_eq!(
+            serialize_level(&parsed),
+            text,
+            "{}: level file changed after reading it back",
+            name
+        );
+        assert_eq!(parsed.hide_wiring, world.hide_wiring, "{}", name);
+        assert_eq!(parsed.channels, world.channels, "{}", name);
+    }
+}
+
+#[test]
+fn mirrors_bounce_beams_into_receivers() {
+    let level = || {
+        LevelBuilder::make_level(4, 4, &[&[true; 4] as &[bool]; 4], WinRequirement::Never)
+            .with_obj((0, 2), ObjectInfo::Laser(Direction::East))
+            .with_obj((2, 0), ObjectInfo::Cat)
+            .with_obj((2, 1), ObjectInfo::Mirror(false))
+            .with_obj((2, 3), ObjectInfo::LaserReceiver((3, 0).into(), 0))
+            .with_obj((3, 0), ObjectInfo::Door(Direction::North, false))
+            .finish()
+    };
+    assert!(!door_open(&level(), (3, 0)));
+    assert_plays("mirrors", level, "DR", |played, world| match played {
+        1 => {
+            assert!(door_open(world, (3, 0)));
+            let beams = world.beams();
+            assert_eq!(beams[0].cells.last(), Some(&(2, 3).into()));
+        }
+        _ => assert_eq!(world.win_state, WinState::Alive),
+    });
+}
+
+#[test]
+fn lasers_burn_boxes_and_cats() {
+    let level = || {
+        LevelBuilder::make_level(4, 3, &[&[true; 4] as &[bool]; 3], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Laser(Direction::East))
+            .with_obj((2, 1), ObjectInfo::Box)
+            .with_obj((2, 2), ObjectInfo::Cat)
+            .finish()
+    };
+    assert_plays("lasers", level, "ULU", |played, world| match played {
+        1 => {
+            assert_eq!(count(world, (2, 0), ObjectInfo::BurntBox), 1);
+            assert_eq!(count(world, (2, 0), ObjectInfo::Fire), 1);
+        }
+        2 => assert_eq!(world.win_state, WinState::Alive),
+        _ => assert_eq!(world.win_state, WinState::Lasered),
+    });
+}
+
+#[test]
+fn objects_slide_across_ice() {
+    let level = || {
+        let mut builder =
+            LevelBuilder::make_level(7, 2, &[&[true; 7], &[true; 7]], WinRequirement::Never)
+                .with_obj((0, 0), ObjectInfo::Cat)
+                .with_obj((2, 0), ObjectInfo::ToggleButton((6, 0).into(), 0))
+                .with_obj((5, 0), ObjectInfo::Box)
+                .with_obj((0, 1), ObjectInfo::Cat)
+                .with_obj((1, 1), ObjectInfo::Box)
+                .with_obj((5, 1), ObjectInfo::Death);
+        for x in 1..4 {
+            builder = builder.with_obj((x, 0), ObjectInfo::Ice);
+        }
+        for x in 2..7 {
+            builder = builder.with_obj((x, 1), ObjectInfo::Ice);
+        }
+        builder.finish()
+    };
+    assert_plays("ice", level, "RR", |played, world| match played {
+        1 => {
+            // The top cat stops on the first cell without ice and flips the button it slid over
+            assert_eq!(count(world, (4, 0), ObjectInfo::Cat), 1);
+            assert_eq!(count(world, (5, 0), ObjectInfo::Box), 1);
+            assert!(world.wiring[6][0]);
+            // The bottom box slides over the acid to the edge of the level
+            assert_eq!(count(world, (6, 1), ObjectInfo::Box), 1);
+            assert_eq!(count(world, (1, 1), ObjectInfo::Cat), 1);
+            assert_eq!(world.win_state, WinState::Alive);
+        }
+        _ => assert_eq!(world.win_state, WinState::Acid),
+    });
+}
+
+#[test]
+fn fire_spreads_until_water() {
+    let level = || {
+        LevelBuilder::make_level(5, 2, &[&[true; 5], &[true; 5]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Fire)
+            .with_obj((1, 0), ObjectInfo::Box)
+            .with_obj((2, 0), ObjectInfo::Goal)
+            .with_obj((3, 0), ObjectInfo::Box)
+            .with_obj((4, 0), ObjectInfo::Water)
+            .with_obj((0, 1), ObjectInfo::Cat)
+            .with_fire_spread(2)
+            .finish()
+    };
+    assert!(!level().fire_spreads_next_move());
+    assert_plays(
+        "spreading fire",
+        level,
+        "RLRLRL",
+        |played, world| match played {
+            1 => {
+                assert!(world.fire_spreads_next_move());
+                assert_eq!(world.next_fires(), [(1, 0).into()]);
+            }
+            2 => {
+                assert_eq!(count(world, (1, 0), ObjectInfo::BurntBox), 1);
+                assert_eq!(count(world, (1, 0), ObjectInfo::Fire), 1);
+            }
+            6 => {
+                assert_eq!(count(world, (2, 0), ObjectInfo::BurntBox), 1);
+                // The box next to the water is soaked, so the fire stops beside it
+                assert_eq!(count(world, (3, 0), ObjectInfo::Box), 1);
+                assert!(world.next_fires().is_empty());
+            }
+            _ => {}
+        },
+    );
+}
+
+#[test]
+fn edge_walls_block_both_ways() {
+    let level = || {
+        LevelBuilder::make_level(4, 2, &[&[true; 4], &[true; 4]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 0), ObjectInfo::Box)
+            .with_obj((3, 0), ObjectInfo::WallLeft(false))
+            .with_obj((0, 1), ObjectInfo::WallBack(false))
+            .with_obj((1, 1), ObjectInfo::Laser(Direction::East))
+            .with_obj((2, 1), ObjectInfo::WallRight(false))
+            .finish()
+    };
+    assert_plays("edge walls", level, "RRLD", |played, world| match played {
+        1 => assert_eq!(count(world, (2, 0), ObjectInfo::Box), 1),
+        2 => {
+            // The wall on the west edge of (3, 0) stops the box from crossing into it
+            assert_eq!(world.move_id, 1);
+            assert_eq!(count(world, (1, 0), ObjectInfo::Cat), 1);
+        }
+        4 => {
+            // The wall on the north edge of (0, 1) stops the cat from going south
+            assert_eq!(world.move_id, 2);
+            assert_eq!(count(world, (0, 0), ObjectInfo::Cat), 1);
+            assert!(world.edge_blocked((0, 0).into(), Direction::South));
+            assert!(world.edge_blocked((0, 1).into(), Direction::North));
+            assert!(!world.edge_blocked((1, 0).into(), Direction::South));
+            // Beams stop at walls too
+            assert_eq!(world.beams()[0].cells, [(2, 1).into()]);
+        }
+        _ => {}
+    });
+    // And so does spreading fire
+    let world = LevelBuilder::make_level(2, 1, &[&[true; 2]], WinRequirement::Never)
+        .with_obj((0, 0), ObjectInfo::Fire)
+        .with_obj((1, 0), ObjectInfo::WallLeft(false))
+        .with_obj((1, 0), ObjectInfo::Box)
+        .with_fire_spread(1)
+        .finish();
+    assert!(world.next_fires().is_empty());
+}
+
+#[test]
+fn keys_unlock_doors() {
+    let red = 0xff0000ff;
+    let blue = 0x0000ffff;
+    let level = || {
+        LevelBuilder::make_level(5, 1, &[&[true; 5]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 0), ObjectInfo::Key(red))
+            .with_obj((2, 0), ObjectInfo::LockedDoor(Direction::East, red))
+            .with_obj((3, 0), ObjectInfo::LockedDoor(Direction::East, blue))
+            .finish()
+    };
+    let cat = level()[Point::from((0, 0))]
+        .iter()
+        .find(|v| v.obj_type == ObjectInfo::Cat)
+        .unwrap()
+        .id;
+    assert_plays("keys", level, "RRR", |played, world| match played {
+        1 => {
+            assert_eq!(count(world, (1, 0), ObjectInfo::Key(red)), 0);
+            assert_eq!(world.keys_held(cat).collect::<Vec<_>>(), [red]);
+        }
+        // The red key opens the red door and is used up
+        2 => {
+            assert_eq!(count(world, (2, 0), ObjectInfo::Cat), 1);
+            let door = ObjectInfo::LockedDoor(Direction::East, red);
+            assert_eq!(count(world, (2, 0), door), 0);
+            assert!(world.keys.is_empty());
+        }
+        // Without a blue key the blue door stays shut
+        _ => {
+            assert_eq!(world.move_id, 2);
+            let door = ObjectInfo::LockedDoor(Direction::East, blue);
+            assert_eq!(count(world, (3, 0), door), 1);
+        }
+    });
+}
+
+#[test]
+fn blocked_doors_keep_their_keys() {
+    let red = 0xff0000ff;
+    let level = || {
+        LevelBuilder::make_level(4, 1, &[&[true; 4]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 0), ObjectInfo::Key(red))
+            .with_obj((2, 0), ObjectInfo::LockedDoor(Direction::East, red))
+            .with_obj((2, 0), ObjectInfo::Box)
+            .with_obj((3, 0), ObjectInfo::Barrier)
+            .finish()
+    };
+    // The box in the doorway can't be pushed, so the door stays locked and the key is kept
+    assert_plays("blocked door", level, "RR", |played, world| {
+        assert_eq!(world.move_id, 1);
+        assert_eq!(count(world, (1, 0), ObjectInfo::Cat), 1);
+        assert_eq!(world.keys.len(), 1);
+        if played == 2 {
+            let door = ObjectInfo::LockedDoor(Direction::East, red);
+            assert_eq!(count(world, (2, 0), door), 1);
+        }
+    });
+}
+
+#[test]
+fn locked_doors_stop_beams() {
+    let world = LevelBuilder::make_level(3, 1, &[&[true; 3]], WinRequirement::Never)
+        .with_obj((0, 0), ObjectInfo::Laser(Direction::East))
+        .with_obj((1, 0), ObjectInfo::LockedDoor(Direction::East, 0xff0000ff))
+        .with_obj((2, 0), ObjectInfo::Box)
+        .finish();
+    assert_eq!(world.beams()[0].cells, [(1, 0).into()]);
+}
+
+#[test]
+fn one_way_gates_only_let_objects_through_forwards() {
+    let level = || {
+        LevelBuilder::make_level(4, 2, &[&[true; 4], &[true; 4]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 0), ObjectInfo::OneWay(Direction::East))
+            .with_obj((1, 1), ObjectInfo::OneWay(Direction::East))
+            .with_obj((2, 1), ObjectInfo::Box)
+            .finish()
+    };
+    assert_plays("one way", level, "RRLRDL", |played, world| match played {
+        2 => assert_eq!(count(world, (2, 0), ObjectInfo::Cat), 1),
+        // Going back the other way is blocked by the gate
+        3 => {
+            assert_eq!(world.move_id, 2);
+            assert_eq!(count(world, (2, 0), ObjectInfo::Cat), 1);
+        }
+        // Boxes can't be pushed against the arrow either
+        6 => {
+            assert_eq!(world.move_id, 4);
+            assert_eq!(count(world, (2, 1), ObjectInfo::Box), 1);
+        }
+        _ => {}
+    });
+}
+
+#[test]
+fn boxes_fill_pits_that_cats_fall_into() {
+    let level = || {
+        LevelBuilder::make_level(5, 1, &[&[true; 5]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 0), ObjectInfo::Box)
+            .with_obj((2, 0), ObjectInfo::Pit(false))
+            .with_obj((4, 0), ObjectInfo::Pit(false))
+            .finish()
+    };
+    assert_plays("pits", level, "RRRRR", |played, world| match played {
+        1 => {
+            assert_eq!(count(world, (2, 0), ObjectInfo::Box), 0);
+            assert_eq!(count(world, (2, 0), ObjectInfo::Pit(true)), 1);
+        }
+        // The filled pit is safe to walk over
+        3 => assert_eq!(world.win_state, WinState::Alive),
+        5 => assert_eq!(world.win_state, WinState::Fell),
+        _ => {}
+    });
+}
+
+#[test]
+fn bouncing_robots_turn_at_boxes() {
+    let level = || {
+        LevelBuilder::make_level(4, 2, &[&[true; 4], &[true; 4]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((1, 1), ObjectInfo::Robot(Roam::Bounce, Direction::East))
+            .with_obj((3, 1), ObjectInfo::Box)
+            .finish()
+    };
+    let robot = |dir| ObjectInfo::Robot(Roam::Bounce, dir);
+    assert_plays(
+        "bouncing robot",
+        level,
+        "RRD",
+        |played, world| match played {
+            1 => assert_eq!(count(world, (2, 1), robot(Direction::East)), 1),
+            2 => assert_eq!(count(world, (1, 1), robot(Direction::West)), 1),
+            _ => {
+                assert_eq!(count(world, (0, 1), robot(Direction::West)), 1);
+                assert_eq!(world.win_state, WinState::Alive);
+            }
+        },
+    );
+}
+
+#[test]
+fn chasing_robots_catch_cats() {
+    let level = || {
+        LevelBuilder::make_level(5, 1, &[&[true; 5]], WinRequirement::Never)
+            .with_obj((0, 0), ObjectInfo::Cat)
+            .with_obj((4, 0), ObjectInfo::Robot(Roam::Chase, Direction::West))
+            .finish()
+    };
+    let robot = ObjectInfo::Robot(Roam::Chase, Direction::West);
+    assert_plays(
+        "chasing robot",
+        level,
+        "RLLR",
+        |played, world| match played {
+            // The third move is blocked, and robots only move when the cat does
+            2 | 3 => assert_eq!(count(world, (2, 0), robot.clone()), 1),
+            4 => assert_eq!(world.win_state, WinState::Caught),
+            _ => {}
+        },
+    );
+}
+
+#[test]
+fn robots_stop_beams() {
+    let world = LevelBuilder::make_level(3, 1, &[&[true; 3]], WinRequirement::Never)
+        .with_obj((0, 0), ObjectInfo::Laser(Direction::East))
+        .with_obj((1, 0), ObjectInfo::Robot(Roam::Patrol, Direction::North))
+        .with_obj((2, 0), ObjectInfo::Box)
+        .finish();
+    assert_eq!(world.beams()[0].cells, [(1, 0).into()]);
+}
+
+#[test]
+fn only_the_active_cat_moves() {
+    let level = || {
+        LevelBuilder::make_level(
+            3,
+            2,
+            &[&[true; 3], &[true; 3]],
+            WinRequirement::CatsInGoals(2),
+        )
+        .with_obj((0, 0), ObjectInfo::Cat)
+        .with_obj((0, 1), ObjectInfo::Cat)
+        .with_obj((1, 0), ObjectInfo::Goal)
+        .with_obj((2, 1), ObjectInfo::Goal)
+        .with_switching_cats()
+        .finish()
+    };
+    // Until the player switches, only the first cat moves
+    assert_plays("switching cats", level, "RR", |played, world| {
+        assert_eq!(count(world, (played as i32, 0), ObjectInfo::Cat), 1);
+        assert_eq!(count(world, (0, 1), ObjectInfo::Cat), 1);
+    });
+    let mut world = level();
+    world.switch_cat();
+    play(&mut world, Direction::East);
+    assert_eq!(count(&world, (0, 0), ObjectInfo::Cat), 1);
+    assert_eq!(count(&world, (1, 1), ObjectInfo::Cat), 1);
+    // Undo leaves the selection alone, since switching isn't a move
+    world.undo();
+    assert_eq!(count(&world, (0, 1), ObjectInfo::Cat), 1);
+    assert_eq!(world.active_cat(), world.cats().last().copied());
+
+    let solution = solve(&level());
+    assert_eq!(solution.move_count(), Some(3));
+    let mut world = level();
+    for action in solution.move_string().unwrap().chars() {
+        match action {
+            'S' => world.switch_cat(),
+            c => play(&mut world, Direction::try_from(c).unwrap()),
+        }
+    }
+    world.check_win();
+    assert_eq!(world.win_state, WinState::Won);
+}
+
+#[test]
+fn hints_are_stale_after_switching_cats() {
+    let mut world = LevelBuilder::make_level(
+        3,
+        2,
+        &[&[true; 3], &[true; 3]],
+        WinRequirement::CatsInGoals(2),
+    )
+    .with_obj((0, 0), ObjectInfo::Cat)
+    .with_obj((0, 1), ObjectInfo::Cat)
+    .with_obj((1, 0), ObjectInfo::Goal)
+    .with_obj((2, 1), ObjectInfo::Goal)
+    .with_switching_cats()
+    .finish();
+    let hint = HintSearch::new(&world);
+    assert!(hint.is_for(&world));
+    world.switch_cat();
+    assert!(!hint.is_for(&world));
+}
@@ -19,7 +19,7 @@ impl<TR: Into<Point>> std::ops::Add<TR> for Point {
     type Output = Point;
     fn add(self, rhs: TR) -> Self::Output {
         let rhs: Point = rhs.into();
-        let lhs: Point = self.into();
+        let lhs: Point = self;
         Point(lhs.0 + rhs.0, lhs.1 + rhs.1)
     }
 }
@@ -42,7 +42,7 @@ impl<TR: Into<Point>> std::ops::Sub<TR> for Point {
     type Output = Point;
     fn sub(self, rhs: TR) -> Self::Output {
         let rhs: Point = rhs.into();
-        let lhs: Point = self.into();
+        let lhs: Point = self;
         Point(lhs.0 - rhs.0, lhs.1 - rhs.1)
     }
 }
@@ -207,3 +207,13 @@ impl TryFrom<char> for Direction {
         }
     }
 }
+impl From<Direction> for char {
+    fn from(value: Direction) -> Self {
+        match value {
+            North => 'U',
+            South => 'D',
+            East => 'R',
+            West => 'L',
+        }
+    }
+}
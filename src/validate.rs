@@ -0,0 +1,254 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    levels::WinRequirement,
+    object::{Object, ObjectInfo},
+    util::{Direction, Point},
+    world::World,
+};
+
+/// Something wrong with a level that would otherwise only be found by playing it
+#[turbo::serialize]
+#[derive(PartialEq)]
+pub enum Problem {
+    /// Contains (button, target) of a button wired outside the grid
+    WireOutside(Point, Point),
+    /// Contains (button, target) of a button wired to a cell with no door, conveyor, or portal
+    WireToNothing(Point, Point),
+    /// Contains (portal, end) of a portal end that has no portal that is open or can be opened
+    PortalEndClosed(Point, Point),
+    /// Contains (cats needed, cats in the level)
+    TooFewCats(usize, usize),
+    /// Contains (cats needed in goals, goals in the level)
+    TooFewGoals(usize, usize),
+    /// Contains the barrier cell that has other objects on it
+    ObjectOnBarrier(Point),
+    /// Contains the cell of a goal no cat can get to
+    UnreachableGoal(Point),
+    /// Contains the name of a channel that is read but that nothing turns on
+    UndrivenChannel(String),
+    /// Contains a locked door whose color has fewer keys than locked doors
+    MissingKey(Point),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Problem::WireOutside(button, target) => f.write_fmt(format_args!(
+                "Button at {} is wired to {}, outside the level",
+                button, target
+            )),
+            Problem::WireToNothing(button, target) => f.write_fmt(format_args!(
+                "Button at {} is wired to {}, which has nothing to power",
+                button, target
+            )),
+            Problem::PortalEndClosed(portal, end) => f.write_fmt(format_args!(
+                "Portal at {} leads to {}, which has no portal that can open",
+                portal, end
+            )),
+            Problem::TooFewCats(needed, cats) => f.write_fmt(format_args!(
+                "{} cats must reach goals but there are only {} cats",
+                needed, cats
+            )),
+            Problem::TooFewGoals(needed, goals) => f.write_fmt(format_args!(
+                "{} cats must reach goals but there are only {} goals",
+                needed, goals
+            )),
+            Problem::ObjectOnBarrier(point) => f.write_fmt(format_args!(
+                "Objects are placed on the barrier at {}",
+                point
+            )),
+            Problem::UnreachableGoal(point) => {
+                f.write_fmt(format_args!("No cat can reach the goal at {}", point))
+            }
+            Problem::MissingKey(point) => f.write_fmt(format_args!(
+                "Locked door at {} doesn't have enough keys of its color",
+                point
+            )),
+            Problem::UndrivenChannel(name) => f.write_fmt(format_args!(
+                "Channel {} is used but nothing turns it on",
+                name
+            )),
+        }
+    }
+}
+
+/// Find problems with a freshly built level without playing it
+pub fn validate(world: &World) -> Vec<Problem> {
+    let mut problems = vec![];
+    let wired: HashSet<Point> = world
+        .cells_iterator()
+        .flat_map(|point| world[point].iter().filter_map(button_target))
+        .collect();
+    let mut cats = 0;
+    let mut goals = 0;
+    let mut driven: HashSet<&str> = world.channels.iter().map(|v| v.as_str()).collect();
+    let mut read: Vec<&str> = vec![];
+    let mut keys: Vec<u32> = vec![];
+    let mut locked: Vec<(Point, u32)> = vec![];
+    for point in world.cells_iterator() {
+        let cell = &world[point];
+        if cell.iter().any(|v| v.obj_type == ObjectInfo::Barrier)
+            && cell.iter().any(|v| v.obj_type != ObjectInfo::Barrier)
+        {
+            problems.push(Problem::ObjectOnBarrier(point));
+        }
+        for obj in cell.iter() {
+            match &obj.obj_type {
+                ObjectInfo::Cat => cats += 1,
+                ObjectInfo::Goal => goals += 1,
+                ObjectInfo::PushButton(target, _)
+                | ObjectInfo::ToggleButton(target, _)
+                | ObjectInfo::LaserReceiver(target, _) => {
+                    if !world.point_inside(*target) {
+                        problems.push(Problem::WireOutside(point, *target));
+                    } else if !world[*target].iter().any(|v| is_wired(&v.obj_type)) {
+                        problems.push(Problem::WireToNothing(point, *target));
+                    }
+                }
+                ObjectInfo::Portal(ends, ..) => {
+                    for end in ends.iter() {
+                        let can_open = world.point_inside(*end)
+                            && world[*end].iter().any(|v| match v.obj_type {
+                                ObjectInfo::Portal(_, open, _) => open || wired.contains(end),
+                                _ => false,
+                            });
+                        if !can_open {
+                            problems.push(Problem::PortalEndClosed(point, *end));
+                        }
+                    }
+                }
+                ObjectInfo::PushSwitch(channel) | ObjectInfo::ToggleSwitch(channel) => {
+                    driven.insert(channel);
+                }
+                ObjectInfo::Gate(_, inputs, output) => {
+                    read.extend(inputs.iter().map(|v| v.as_str()));
+                    driven.insert(output);
+                }
+                ObjectInfo::Delay(input, output, ..) => {
+                    read.push(input);
+                    driven.insert(output);
+                }
+                ObjectInfo::Latch(set, reset, output, _) => {
+                    read.push(set);
+                    read.push(reset);
+                    driven.insert(output);
+                }
+                ObjectInfo::Key(color) => keys.push(*color),
+                ObjectInfo::LockedDoor(_, color) => locked.push((point, *color)),
+                ObjectInfo::Relay(channel, _) => {
+                    read.push(channel);
+                    if !cell.iter().any(|v| is_wired(&v.obj_type)) {
+                        problems.push(Problem::WireToNothing(point, point));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    for (point, color) in locked.iter() {
+        let needed = locked.iter().filter(|v| v.1 == *color).count();
+        if keys.iter().filter(|v| *v == color).count() < needed {
+            problems.push(Problem::MissingKey(*point));
+        }
+    }
+    let mut undriven: Vec<&str> = read.into_iter().filter(|v| !driven.contains(v)).collect();
+    undriven.sort();
+    undriven.dedup();
+    problems.extend(
+        undriven
+            .into_iter()
+            .map(|v| Problem::UndrivenChannel(v.to_string())),
+    );
+    // A portal with several ends copies whatever goes through it, so there is no telling how many there will be
+    let copies = world.cells_iterator().any(|point| {
+        world[point]
+            .iter()
+            .any(|v| matches!(&v.obj_type, ObjectInfo::Portal(ends, ..) if ends.len() > 1))
+    });
+    for req in world.requirements.iter() {
+        if let WinRequirement::CatsInGoals(needed) = *req
+            && !copies
+        {
+            if needed > cats {
+                problems.push(Problem::TooFewCats(needed, cats));
+            }
+            if needed > goals {
+                problems.push(Problem::TooFewGoals(needed, goals));
+            }
+        }
+    }
+    let reachable = reachable_cells(world);
+    for point in world.cells_iterator() {
+        if !reachable.contains(&point)
+            && world[point].iter().any(|v| v.obj_type == ObjectInfo::Goal)
+        {
+            problems.push(Problem::UnreachableGoal(point));
+        }
+    }
+    problems
+}
+
+fn button_target(obj: &Object) -> Option<Point> {
+    match obj.obj_type {
+        ObjectInfo::PushButton(target, _)
+        | ObjectInfo::ToggleButton(target, _)
+        | ObjectInfo::LaserReceiver(target, _) => Some(target),
+        _ => None,
+    }
+}
+
+/// Returns true if wiring changes the object
+fn is_wired(obj: &ObjectInfo) -> bool {
+    matches!(
+        obj,
+        ObjectInfo::Door(..)
+            | ObjectInfo::ToggleableConveyor(..)
+            | ObjectInfo::RotateableConveyor(..)
+            | ObjectInfo::Portal(..)
+            | ObjectInfo::RotateableMirror(..)
+    )
+}
+
+/// Returns true if a one-way gate stops objects at point from moving in the direction dir
+fn one_way_blocked(world: &World, point: Point, dir: Direction) -> bool {
+    world.point_inside(point + dir)
+        && world[point + dir]
+            .iter()
+            .any(|v| matches!(v.obj_type, ObjectInfo::OneWay(allowed) if allowed != dir))
+}
+
+/// Cells a cat could walk or teleport to if every door opened and every box moved out of the way.
+/// Walls on cell edges and one-way gates facing the other way still block.
+fn reachable_cells(world: &World) -> HashSet<Point> {
+    let mut queue: VecDeque<Point> = world
+        .cells_iterator()
+        .filter(|point| world[*point].iter().any(|v| v.obj_type == ObjectInfo::Cat))
+        .collect();
+    let mut reached: HashSet<Point> = queue.iter().copied().collect();
+    while let Some(point) = queue.pop_front() {
+        let mut next: Vec<Point> = Direction::iter_all()
+            .filter(|dir| !world.edge_blocked(point, *dir) && !one_way_blocked(world, point, *dir))
+            .map(|dir| point + dir)
+            .collect();
+        for obj in world[point].iter() {
+            if let ObjectInfo::Portal(ends, ..) = &obj.obj_type {
+                next.extend(ends.iter().copied());
+            }
+        }
+        for neighbor in next {
+            if world.point_inside(neighbor)
+                && !world[neighbor]
+                    .iter()
+                    .any(|v| v.obj_type == ObjectInfo::Barrier)
+                && reached.insert(neighbor)
+            {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    reached
+}
@@ -1,34 +1,134 @@
 use std::{
+    collections::BTreeSet,
     fmt::Write,
     ops::{Index, IndexMut},
 };
 
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
 use crate::{
     levels::{WinRequirement, WinState},
-    menu::button_held,
-    object::{MoveType, Object, ObjectInfo},
+    object::{MoveType, Object, ObjectId, ObjectInfo},
     util::{Direction, Point},
 };
-use turbo::*;
 
-#[turbo::serialize]
-#[derive(PartialEq)]
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 pub enum Edit {
     /// Contains (point, index, old_activity)
     Wiring(Point, usize, bool),
-    /// Contains (old_location, old_index, new_location)
-    MoveObject(Point, usize, Point),
-    /// Contains (point, usize)
-    SummonObject(Point, usize),
-    /// Contains (point, index, what was removed)
-    DeleteObject(Point, usize, ObjectInfo),
-    /// Contains (point, index, old_info)
-    ChangeObjInfo(Point, usize, ObjectInfo),
-    /// Contains (point, index, old_animation_tween)
-    SetAnimation(Point, usize, i32),
+    /// Contains (object, old_location, old_index, new_location)
+    MoveObject(ObjectId, Point, usize, Point),
+    /// Contains (point, object)
+    SummonObject(Point, ObjectId),
+    /// Contains (point, index, object, what was removed)
+    DeleteObject(Point, usize, ObjectId, ObjectInfo),
+    /// Contains (point, object, old_info)
+    ChangeObjInfo(Point, ObjectId, ObjectInfo),
+    /// Contains (point, object, old_animation_tween)
+    SetAnimation(Point, ObjectId, i32),
+    /// Contains (object, location, old_location) of an object that undo moved back.
+    /// Only found in the redo history.
+    UnmoveObject(ObjectId, Point, Point),
+    /// Contains (channel, old_activity)
+    Channel(String, bool),
+    /// Contains (cat, color) of a key the cat was given
+    GiveKey(ObjectId, u32),
+    /// Contains (cat, color) of a key taken from the cat
+    TakeKey(ObjectId, u32),
+    /// Contains the previously selected cat
+    SelectCat(Option<ObjectId>),
+}
+
+/// Sound effects that the world can ask the game to play
+#[derive(
+    Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize, Serialize, Deserialize,
+)]
+pub enum Sound {
+    AcidBubbles,
+    Conveyor,
+    Door,
+    Fire,
+    FireOut,
+    Meow,
+    Win,
+}
+
+impl Sound {
+    /// Name of the audio file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sound::AcidBubbles => "acid_bubbles",
+            Sound::Conveyor => "conveyor",
+            Sound::Door => "door",
+            Sound::Fire => "fire",
+            Sound::FireOut => "fire_out",
+            Sound::Meow => "meow",
+            Sound::Win => "win",
+        }
+    }
+}
+
+/// Something that happened while running the rules, for the game to present
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+pub enum WorldEvent {
+    Sound(Sound),
+    /// The cats died in the given way
+    Died(WinState),
+    /// Every win requirement was met
+    Won,
+}
+
+/// Value drawn sliding from start to end over duration ticks.
+/// The caller passes in the current tick, so the rules can start slides without a clock of their own.
+#[derive(
+    Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize, Serialize, Deserialize,
+)]
+pub struct Tween {
+    pub start: i32,
+    pub end: i32,
+    pub duration: usize,
+    /// Tick the slide started at
+    pub start_tick: usize,
+}
+
+impl Tween {
+    pub const fn new(value: i32) -> Self {
+        Self {
+            start: value,
+            end: value,
+            duration: 0,
+            start_tick: 0,
+        }
+    }
+    pub const fn duration(mut self, duration: usize) -> Self {
+        self.duration = duration;
+        self
+    }
+    pub fn set_duration(&mut self, duration: usize) {
+        self.duration = duration;
+    }
+    /// Start sliding toward end from wherever the value is at tick now
+    pub fn set(&mut self, end: i32, now: usize) {
+        if end == self.end {
+            return;
+        }
+        self.start = self.get(now);
+        self.end = end;
+        self.start_tick = now;
+    }
+    /// Value at tick now
+    pub fn get(&self, now: usize) -> i32 {
+        let elapsed = now.saturating_sub(self.start_tick);
+        if elapsed >= self.duration {
+            return self.end;
+        }
+        let t = elapsed as f64 / self.duration as f64;
+        (self.start as f64 + (self.end - self.start) as f64 * t) as i32
+    }
 }
 
-#[turbo::serialize]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 pub struct World {
     /// Width of the world
     pub width: usize,
@@ -40,18 +140,41 @@ pub struct World {
     pub requirements: Vec<WinRequirement>,
     /// List of wires, each have four inputs
     pub wiring: Vec<[bool; 4]>,
+    /// Named signals driven by switches and gates that are on
+    pub channels: BTreeSet<String>,
+    /// Keys cats are carrying, as sorted (cat, key color)
+    pub keys: Vec<(ObjectId, u32)>,
+    /// Only one cat moves at a time, and the player switches between them
+    pub switch_cats: bool,
+    /// Cat the player switched to, if it still exists. See `World::active_cat`
+    pub selected_cat: Option<ObjectId>,
     /// How many moves have been done
     pub move_id: usize,
     /// List of (move_id, edit)
     pub edit_history: Vec<(usize, Edit)>,
+    /// Moves that have been undone, latest last, as the win state before the undo and
+    /// the edits that redo the move in the order they should be reverted
+    pub redo_history: Vec<(WinState, Vec<Edit>)>,
     /// Current win state (win, dead, or playing)
     pub win_state: WinState,
     /// Caption to always display
     pub caption: String,
     /// Hint to only show when the user clicks the button
     pub hint: String,
+    /// Fewest moves the level can be won in, used to award stars
+    pub par: Option<usize>,
+    /// Never draw the wiring overlay, for levels where working out the wiring is the puzzle
+    pub hide_wiring: bool,
+    /// Fire spreads to neighboring boxes and goals once every this many moves
+    pub fire_spread: Option<usize>,
     /// Conveyor belt timer
     pub conveyance: u32,
+    /// Events that have not been handled by the game yet
+    pub events: Vec<WorldEvent>,
+    /// ID given to the next object summoned
+    pub next_id: u32,
+    /// Tick of the frame being run, which slides started by moves begin at. Set by the game each frame
+    pub tick: usize,
 }
 
 impl World {
@@ -61,63 +184,135 @@ impl World {
     }
     /// Undo the previous move
     pub fn undo(&mut self) {
+        let win_state = self.win_state;
         self.win_state = WinState::Alive;
         if self.move_id == 0 {
             return;
         }
-        self.move_id = self.move_id - 1;
+        self.move_id -= 1;
+        let mut redo = vec![];
         while let Some((_, edit)) = self.edit_history.pop_if(|v| v.0 == self.move_id) {
-            match edit {
-                Edit::ChangeObjInfo(point, idx, info) => {
-                    if matches!(info, ObjectInfo::Door(..)) {
-                        audio::play("door");
-                    }
-                    self[point][idx].obj_type = info;
-                }
-                Edit::MoveObject(old_point, idx, new_point) => {
-                    let Some(mut obj) = self[new_point].pop() else {
-                        panic!();
-                    };
-                    obj.position = old_point;
-                    self[old_point].insert(idx, obj);
-                    let new_pos = World::to_screen_space(old_point);
-                    self[old_point][idx].draw_pos.0.set(new_pos.0);
-                    self[old_point][idx].draw_pos.1.set(new_pos.1);
-                }
-                Edit::Wiring(point, idx, active) => {
-                    self.wiring[point.x() as usize + point.y() as usize * self.width][idx] = active;
-                }
-                Edit::SetAnimation(point, idx, anim) => {
-                    self[point][idx].animation.set(anim);
-                }
-                Edit::DeleteObject(point, idx, obj) => {
-                    let pos = World::to_screen_space(point);
-                    self[point].insert(
-                        idx,
-                        Object {
-                            animation: Tween::new(0),
-                            obj_type: obj,
-                            draw_pos: (
-                                Tween::new(pos.0).duration(10),
-                                Tween::new(pos.1).duration(10),
-                            ),
-                            facing: Direction::East,
-                            position: point,
-                        },
-                    );
-                }
-                Edit::SummonObject(point, idx) => {
-                    self[point].remove(idx);
+            redo.push(self.revert(edit));
+        }
+        self.redo_history.push((win_state, redo));
+    }
+    /// Redo the last undone move
+    pub fn redo(&mut self) {
+        if self.win_state != WinState::Alive {
+            return;
+        }
+        let Some((win_state, edits)) = self.redo_history.pop() else {
+            return;
+        };
+        for edit in edits.into_iter().rev() {
+            let edit = self.revert(edit);
+            self.edit_history.push((self.move_id, edit));
+        }
+        self.move_id += 1;
+        // A win is picked up again by check_win
+        if win_state.is_dead() {
+            self.die(win_state);
+        }
+    }
+    /// Put back the change recorded by an edit, returning the edit that would reapply the change
+    fn revert(&mut self, edit: Edit) -> Edit {
+        match edit {
+            Edit::ChangeObjInfo(point, id, info) => {
+                if matches!(info, ObjectInfo::Door(..)) {
+                    self.events.push(WorldEvent::Sound(Sound::Door));
                 }
+                let idx = self.index_of(point, id);
+                let new_info = std::mem::replace(&mut self[point][idx].obj_type, info);
+                Edit::ChangeObjInfo(point, id, new_info)
+            }
+            Edit::MoveObject(id, old_point, idx, new_point) => {
+                let new_idx = self.index_of(new_point, id);
+                let mut obj = self[new_point].remove(new_idx);
+                obj.position = old_point;
+                self[old_point].insert(idx, obj);
+                let new_pos = World::to_screen_space(old_point);
+                let now = self.tick;
+                self[old_point][idx].draw_pos.0.set(new_pos.0, now);
+                self[old_point][idx].draw_pos.1.set(new_pos.1, now);
+                Edit::UnmoveObject(id, old_point, new_point)
+            }
+            Edit::UnmoveObject(id, point, old_point) => {
+                let idx = self.index_of(point, id);
+                let mut obj = self[point].remove(idx);
+                obj.position = old_point;
+                let new_pos = World::to_screen_space(old_point);
+                obj.draw_pos.0.set_duration(10);
+                obj.draw_pos.1.set_duration(10);
+                obj.draw_pos.0.set(new_pos.0, self.tick);
+                obj.draw_pos.1.set(new_pos.1, self.tick);
+                self[old_point].push(obj);
+                Edit::MoveObject(id, point, idx, old_point)
+            }
+            Edit::Wiring(point, idx, active) => {
+                let wire = &mut self.wiring[point.x() as usize + point.y() as usize * self.width];
+                let new_active = std::mem::replace(&mut wire[idx], active);
+                Edit::Wiring(point, idx, new_active)
             }
+            Edit::SetAnimation(point, id, anim) => {
+                let idx = self.index_of(point, id);
+                let new_anim = self[point][idx].animation.end;
+                let now = self.tick;
+                self[point][idx].animation.set(anim, now);
+                Edit::SetAnimation(point, id, new_anim)
+            }
+            Edit::DeleteObject(point, idx, id, obj) => {
+                let pos = World::to_screen_space(point);
+                self[point].insert(
+                    idx,
+                    Object {
+                        id,
+                        animation: Tween::new(0),
+                        obj_type: obj,
+                        draw_pos: (
+                            Tween::new(pos.0).duration(10),
+                            Tween::new(pos.1).duration(10),
+                        ),
+                        facing: Direction::East,
+                        position: point,
+                    },
+                );
+                Edit::SummonObject(point, id)
+            }
+            Edit::SummonObject(point, id) => {
+                let idx = self.index_of(point, id);
+                let obj = self[point].remove(idx);
+                Edit::DeleteObject(point, idx, id, obj.obj_type)
+            }
+            Edit::Channel(name, active) => {
+                let new_active = self.channel(&name);
+                self.store_channel(&name, active);
+                Edit::Channel(name, new_active)
+            }
+            Edit::GiveKey(cat, color) => {
+                self.store_key(cat, color, false);
+                Edit::TakeKey(cat, color)
+            }
+            Edit::TakeKey(cat, color) => {
+                self.store_key(cat, color, true);
+                Edit::GiveKey(cat, color)
+            }
+            Edit::SelectCat(cat) => Edit::SelectCat(std::mem::replace(&mut self.selected_cat, cat)),
         }
     }
-    /// Returns true if the world is in a win state
+    /// Position in the cell's stack of the object with the given ID
+    fn index_of(&self, point: Point, id: ObjectId) -> usize {
+        self[point]
+            .iter()
+            .position(|v| v.id == id)
+            .unwrap_or_else(|| panic!("Object {:?} is not at {:?}", id, point))
+    }
+    /// Set the win state if every win requirement is met
     pub fn check_win(&mut self) {
         let reqs = self.win_requirements();
         if reqs.iter().all(|v| v.0) && self.win_state != WinState::Won {
             self.win_state = WinState::Won;
-            audio::play("win");
+            self.events.push(WorldEvent::Won);
+            self.events.push(WorldEvent::Sound(Sound::Win));
             self.move_id -= 1;
             for point in self.cells_iterator() {
                 for i in 0..self[point].len() {
@@ -131,112 +326,35 @@ impl World {
             }
             self.move_id += 1;
         }
-        for i in 0..reqs.len() {
-            let color = if reqs[i].0 { 0x3fb84aff } else { 0xFFFFFFFF };
-            text!(
-                reqs[i].1.as_str(),
-                x = 120 + i * 120,
-                y = 6,
-                fixed = true,
-                color = color
-            );
-        }
     }
-    /// Summons an object at that point
-    pub fn summon_object(&mut self, point: Point, obj: ObjectInfo) {
+    /// Summons an object at that point, returning its new ID
+    pub fn summon_object(&mut self, point: Point, obj: ObjectInfo) -> ObjectId {
         assert!(
             self.point_inside(point),
             "Point {:?} is outside the world",
             point
         );
         let draw_pos = World::to_screen_space(point);
+        let id = ObjectId(self.next_id);
+        self.next_id += 1;
         self[point].push(Object {
+            id,
             obj_type: obj,
             draw_pos: (Tween::new(draw_pos.0), Tween::new(draw_pos.1)),
             facing: Direction::East,
             position: point,
             animation: Tween::new(0),
         });
+        id
     }
-    /// Draw the whole world
-    pub fn draw(&mut self) {
-        // Draw floors
-        for y in 0..self.height {
-            for x in (0..self.width).rev() {
-                if !self[(x, y).into()]
-                    .iter()
-                    .any(|v| v.obj_type == ObjectInfo::Barrier)
-                {
-                    let pos = World::to_screen_space((x, y).into());
-                    sprite!(
-                        ["factory/floor3", "factory/floor3_1"][(x + y) as usize % 2],
-                        x = pos.0,
-                        y = pos.1
-                    );
-                }
-            }
-        }
-        // Tuples of (location, index, z-index)
-        let mut draw_array: Vec<(Point, usize, i32)> = vec![];
-        // Iterate over all grid cells and add to sprite list
-        for y in 0..self.height {
-            for x in (0..self.width).rev() {
-                let pos = (x, y).into();
-                for i in 0..self[pos].len() {
-                    let mut world_pos = self[pos][i].draw_pos;
-                    let z_index = self[pos][i].draw_height() + world_pos.1.get() * 50
-                        - world_pos.0.get() * 25;
-                    draw_array.push((pos, i, z_index));
-                }
-            }
-        }
-        // Sort sprite list by z-index
-        draw_array.sort_by_key(|v| v.2);
-        // Draw items in sprite array
-        for (position, index, _) in draw_array {
-            self[position][index].draw();
-        }
-        // Draw move count text
-        let move_count = format!("Moves: {}", self.move_id);
-        text!(move_count.as_str(), x = 35, y = 6, fixed = true);
-        // Hint button
-        let button_bounds = Bounds::with_size(50, 20)
-            .anchor_right(&turbo::screen())
-            .anchor_top(&turbo::screen())
-            .translate_y(5)
-            .translate_x(-5);
-        if self.hint.len() != 0 && button_held("See hint", button_bounds, 0x888888FF, 0x777777FF) {
-            text_box!(
-                self.hint.as_str(),
-                bounds = button_bounds
-                    .left_of_self()
-                    .adjust_width(200)
-                    .translate_x(-205),
-                align = "right",
-                fixed = true,
-            );
-        }
-        // Draw caption
-        text_box!(
-            self.caption.as_str(),
-            bounds = Bounds::with_size(350, 40)
-                .anchor_center_x(&turbo::screen())
-                .anchor_bottom(&turbo::screen()),
-            align = "center",
-            fixed = true,
-        );
-        if self.conveyance != 0 {
-            sprite!(
-                "loading",
-                scale_x = 2.0,
-                scale_y = 2.0,
-                y = turbo::screen().bottom() - 20,
-                x = 5,
-                fixed = true
-            );
-        }
-    }
-    pub fn cells_iterator<'a>(&'a self) -> impl Iterator<Item = Point> + use<> {
+    /// Convert screen space point to the world space cell that contains it
+    pub fn from_screen_space(x: i32, y: i32) -> Point {
+        let cell_y = y.div_euclid(28);
+        // Floors are slanted, so the left edge moves left by half a pixel per row
+        let x = x - cell_y * 14 - 14 + (y - cell_y * 28) / 2;
+        Point::from((x.div_euclid(38), cell_y))
+    }
+    pub fn cells_iterator(&self) -> impl Iterator<Item = Point> + use<> {
         let width = self.width;
         (0..(self.width * self.height))
             .map(move |v| Point::from(((v % width) as i32, (v / width) as i32)))
@@ -244,8 +362,8 @@ impl World {
     /// Iterate over which order the points should be pushed in if going in a certain direction.
     /// For example, if we are pushing West, we would want to start from the left and end with the right.
     pub fn push_order_points(&self, dir: Direction) -> impl Iterator<Item = Point> + use<> {
-        let width = self.width.clone();
-        let height = self.height.clone();
+        let width = self.width;
+        let height = self.height;
         match dir {
             Direction::East => Box::new(
                 (0..(width - 1))
@@ -272,13 +390,14 @@ impl World {
             self.move_id -= 1;
             let mut movements = vec![];
             for position in self.cells_iterator() {
-                let mut push_proposal = [false; 8];
+                let mut push_proposal = vec![false; self[position].len()];
                 let mut dir: Option<Direction> = None;
                 for (i, cell) in self[position].iter().enumerate() {
                     if cell.obj_type == ObjectInfo::Cat
                         || cell.obj_type == ObjectInfo::Goal
                         || cell.obj_type == ObjectInfo::Box
                         || cell.obj_type == ObjectInfo::Water
+                        || matches!(cell.obj_type, ObjectInfo::Mirror(_))
                     {
                         push_proposal[i] = true;
                     }
@@ -292,17 +411,18 @@ impl World {
                     }
                 }
                 if let Some(dir) = dir
-                    && push_proposal != [false; 8]
+                    && push_proposal.contains(&true)
                 {
                     movements.push((dir, position, push_proposal));
                 }
             }
-            if movements.len() != 0 && self.win_state != WinState::ConstructingLevel {
-                audio::play("conveyor")
+            if !movements.is_empty() && self.win_state != WinState::ConstructingLevel {
+                self.events.push(WorldEvent::Sound(Sound::Conveyor));
             }
             for (dir, position, push_proposal) in movements {
                 self.try_movement(dir, position, push_proposal);
             }
+            self.fire_lasers();
             self.move_id += 1;
         }
         self.conveyance = self.conveyance.max(1) - 1;
@@ -311,7 +431,7 @@ impl World {
     pub fn movement(&mut self, dir: Direction) {
         let num_edits_before = self.edit_history.len();
         for position in self.push_order_points(dir) {
-            let mut push_proposal = [false; 8];
+            let mut push_proposal = vec![false; self[position].len()];
             for (i, cell) in self[position].iter().enumerate() {
                 if cell.does_move(self)
                     && self
@@ -319,7 +439,7 @@ impl World {
                         .iter()
                         .rev()
                         .take_while(|v| v.0 == self.move_id)
-                        .all(|v| !matches!(v.1,Edit::MoveObject(p1, _, p2) if p1 == position - dir && p2 == position))
+                        .all(|v| !matches!(v.1,Edit::MoveObject(_, p1, _, p2) if p1 == position - dir && p2 == position))
                 {
                     push_proposal[i] = true;
                 }
@@ -327,36 +447,40 @@ impl World {
             self.try_movement(dir, position, push_proposal);
         }
         if num_edits_before != self.edit_history.len() {
+            self.move_robots();
+            self.fire_lasers();
+            self.spread_fire();
+            self.tick_delays();
             self.move_id += 1;
+            self.redo_history.clear();
         }
     }
-    /// Try to move the cells at point in the bool array "push_proposal" in the direction dir.
-    /// Moves as many of the attempted cells as possible
-    pub fn try_movement(&mut self, dir: Direction, point: Point, mut push_proposal: [bool; 8]) {
+    /// Try to move the objects at point in the direction dir.
+    /// "push_proposal" has an entry for each object in the cell, true for the ones to move.
+    /// Moves as many of the attempted objects as possible
+    pub fn try_movement(&mut self, dir: Direction, point: Point, mut push_proposal: Vec<bool>) {
         // Previous caller is requesting the objects in push_proposal at point to be pushed toward dir
 
-        // If the place we are pushing to is outside the world, it will fail
-        if !self.point_inside(point + dir) {
+        // If the place we are pushing to is outside the world or walled off, it will fail
+        if !self.point_inside(point + dir) || self.edge_blocked(point, dir) {
             return;
         }
         // If there is no request, end
-        if push_proposal == [false; 8] {
+        if !push_proposal.contains(&true) {
             return;
         }
+        // Earlier movements can have added objects since the proposal was made
+        push_proposal.resize(self[point].len(), false);
         // Determine who we must push
-        let mut next_push = [false; 8];
+        let mut next_push = vec![false; self[point + dir].len()];
         for (j, current_push) in self[point].iter().enumerate() {
             if !push_proposal[j] {
                 continue;
             }
-            let mut move_abilities = [MoveType::MoveOver; 8];
-            self[point + dir]
+            let move_abilities: Vec<MoveType> = self[point + dir]
                 .iter()
-                .map(|item| item.test_push_by(&current_push.obj_type))
-                .enumerate()
-                .for_each(|(i, v)| {
-                    move_abilities[i] = v;
-                });
+                .map(|item| self.push_ability(item, current_push, dir))
+                .collect();
             if move_abilities.contains(&MoveType::NotAllowed) {
                 push_proposal[j] = false;
                 continue;
@@ -369,9 +493,11 @@ impl World {
         }
         // Try pushing the next cell
         self.try_movement(dir, point + dir, next_push);
+        // Portals on the way can add or remove objects here
+        push_proposal.resize(self[point].len(), false);
         for item in self[point + dir].iter() {
             for (j, current_push) in self[point].iter().enumerate() {
-                match item.test_push_by(&current_push.obj_type) {
+                match self.push_ability(item, current_push, dir) {
                     MoveType::MoveOver => {}
                     MoveType::NotAllowed | MoveType::Push => {
                         // Object j cannot be moved
@@ -383,11 +509,36 @@ impl World {
         // Move the cells
         let old_dst = self[point + dir].clone();
         let old_src = self[point].clone();
+        let moved: Vec<ObjectId> = (0..push_proposal.len())
+            .filter(|i| push_proposal[*i])
+            .map(|i| self[point][i].id)
+            .collect();
         for (i, _) in push_proposal.iter().enumerate().rev().filter(|(_, m)| **m) {
             self.move_to(point, i, dir);
         }
+        // Cats with the right keys unlock the doors they walked into
+        for cat in moved.iter() {
+            if self[point + dir]
+                .iter()
+                .any(|v| v.id == *cat && v.obj_type == ObjectInfo::Cat)
+            {
+                self.unlock(point + dir, *cat);
+            }
+        }
         self.update_cell(point + dir, &old_dst, dir);
         self.update_cell(point, &old_src, dir);
+        // Objects that moved onto ice keep going until they are stopped
+        if self[point + dir]
+            .iter()
+            .any(|v| v.obj_type == ObjectInfo::Ice)
+            && !self.win_state.is_dead()
+        {
+            let slide: Vec<bool> = self[point + dir]
+                .iter()
+                .map(|v| moved.contains(&v.id) && v.slides())
+                .collect();
+            self.try_movement(dir, point + dir, slide);
+        }
     }
     /// Returns true if a point is inside the Grid
     pub fn point_inside(&self, point: Point) -> bool {
@@ -396,46 +547,84 @@ impl World {
             && point.y() >= 0
             && point.y() < self.height as i32
     }
+    /// Objects at a point, or None if the point is outside the grid
+    pub fn get(&self, point: Point) -> Option<&Vec<Object>> {
+        self.point_inside(point)
+            .then(|| &self.inner[point.y() as usize * self.width + point.x() as usize])
+    }
+    /// Objects at a point, or None if the point is outside the grid
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut Vec<Object>> {
+        if !self.point_inside(point) {
+            return None;
+        }
+        Some(&mut self.inner[point.y() as usize * self.width + point.x() as usize])
+    }
+    /// Returns true if a wall stands on the edge between the point and its neighbor in the direction dir
+    pub fn edge_blocked(&self, point: Point, dir: Direction) -> bool {
+        let walled = |point: Point, dir: Direction| {
+            self.get(point)
+                .is_some_and(|cell| cell.iter().any(|v| v.wall_edge() == Some(dir)))
+        };
+        walled(point, dir) || walled(point + dir, dir.opposite())
+    }
     /// Move item at point old_location and index in the direction dir
     pub fn move_to(&mut self, old_location: Point, index: usize, dir: Direction) {
         let mut obj = self[old_location].remove(index);
         self.edit_history.push((
             self.move_id,
-            Edit::MoveObject(old_location, index, old_location + dir),
+            Edit::MoveObject(obj.id, old_location, index, old_location + dir),
         ));
         obj.facing = dir;
         obj.position = old_location + dir;
         let new_world_pos = World::to_screen_space(old_location + dir);
         obj.draw_pos.0.set_duration(10);
         obj.draw_pos.1.set_duration(10);
-        obj.draw_pos.0.set(new_world_pos.0);
-        obj.draw_pos.1.set(new_world_pos.1);
+        obj.draw_pos.0.set(new_world_pos.0, self.tick);
+        obj.draw_pos.1.set(new_world_pos.1, self.tick);
         self[old_location + dir].push(obj);
     }
+    /// Returns true if something on the point is heavy enough to press buttons and switches
+    pub fn covered(&self, point: Point) -> bool {
+        self[point].iter().any(|v| {
+            v.obj_type == ObjectInfo::Box
+                || v.obj_type == ObjectInfo::Cat
+                || v.obj_type == ObjectInfo::Goal
+                || v.obj_type == ObjectInfo::Water
+                || matches!(v.obj_type, ObjectInfo::Mirror(_))
+        })
+    }
     /// Set new items in a cell
     /// This function checks for button presses
+    // Several arms only act when a call with side effects succeeds, which doesn't belong in a match guard
+    #[allow(clippy::collapsible_match)]
     pub fn update_cell(&mut self, point: Point, old: &Vec<Object>, direction: Direction) {
         if self[point] == *old {
             return;
         }
-        let covered = self[point].iter().any(|v| {
-            v.obj_type == ObjectInfo::Box
-                || v.obj_type == ObjectInfo::Cat
-                || v.obj_type == ObjectInfo::Goal
-                || v.obj_type == ObjectInfo::Water
-        });
+        self.pick_up_keys(point);
+        self.fill_pit(point);
+        let covered = self.covered(point);
         let has_acid = self[point].iter().any(|v| v.obj_type == ObjectInfo::Death);
         let has_fire = self[point].iter().any(|v| v.obj_type == ObjectInfo::Fire);
+        let has_pit = self[point]
+            .iter()
+            .any(|v| v.obj_type == ObjectInfo::Pit(false));
         let has_cat = self[point].iter().any(|v| v.obj_type == ObjectInfo::Cat);
         if has_acid && has_cat {
-            audio::play("acid_bubbles");
-            audio::play("meow");
-            self.win_state = WinState::Acid;
+            self.die(WinState::Acid);
         }
         if has_fire && has_cat {
-            audio::play("meow");
-            audio::play("fire");
-            self.win_state = WinState::Burnt;
+            self.die(WinState::Burnt);
+        }
+        if has_pit && has_cat {
+            self.die(WinState::Fell);
+        }
+        if has_cat
+            && self[point]
+                .iter()
+                .any(|v| matches!(v.obj_type, ObjectInfo::Robot(..)))
+        {
+            self.die(WinState::Caught);
         }
         let mut i = 0;
         while i < self[point].len() {
@@ -455,6 +644,23 @@ impl World {
                         }
                     }
                 }
+                ObjectInfo::PushSwitch(channel) => {
+                    let anim = if covered { 1 } else { 0 };
+                    if self[point][i].animation.end != anim {
+                        self.set_animation(point, i, anim, 1);
+                    }
+                    if self.set_channel(&channel, self.push_switch_held(&channel)) {
+                        self.propagate_signals();
+                    }
+                }
+                ObjectInfo::ToggleSwitch(channel) => {
+                    if covered {
+                        let on = !self.channel(&channel);
+                        self.set_channel(&channel, on);
+                        self.set_animation(point, i, if on { 1 } else { 0 }, 1);
+                        self.propagate_signals();
+                    }
+                }
                 ObjectInfo::RotateableConveyor(_, _, _)
                 | ObjectInfo::ToggleableConveyor(_, true) => {
                     if covered {
@@ -463,42 +669,46 @@ impl World {
                 }
                 ObjectInfo::Cat => {
                     if has_acid {
-                        audio::play("acid_bubbles");
-                        audio::play("meow");
-                        self.win_state = WinState::Acid;
+                        self.die(WinState::Acid);
                     }
                     if has_fire && has_cat {
-                        audio::play("meow");
-                        audio::play("fire");
+                        self.die(WinState::Burnt);
                         self.edit_history.push((
                             self.move_id,
-                            Edit::ChangeObjInfo(point, i, self[point][i].obj_type.clone()),
+                            Edit::ChangeObjInfo(
+                                point,
+                                self[point][i].id,
+                                self[point][i].obj_type.clone(),
+                            ),
                         ));
                         self[point][i].obj_type = ObjectInfo::BurntBox;
                         self.set_animation(point, i, 10, 30);
-                        self.win_state = WinState::Burnt;
                     }
                 }
                 ObjectInfo::Box | ObjectInfo::Goal => {
                     if has_fire {
                         self.edit_history.push((
                             self.move_id,
-                            Edit::ChangeObjInfo(point, i, self[point][i].obj_type.clone()),
+                            Edit::ChangeObjInfo(
+                                point,
+                                self[point][i].id,
+                                self[point][i].obj_type.clone(),
+                            ),
                         ));
                         self[point][i].obj_type = ObjectInfo::BurntBox;
-                        audio::play("fire");
+                        self.events.push(WorldEvent::Sound(Sound::Fire));
                         self.set_animation(point, i, 10, 30);
                     }
                 }
                 ObjectInfo::Water => {
                     if has_fire {
-                        audio::play("fire_out");
+                        self.events.push(WorldEvent::Sound(Sound::FireOut));
                         for k in 0..self[point].len() {
                             if self[point][k].obj_type == ObjectInfo::Fire {
                                 self[point][k].obj_type = ObjectInfo::FireOut;
                                 self.edit_history.push((
                                     self.move_id,
-                                    Edit::ChangeObjInfo(point, k, ObjectInfo::Fire),
+                                    Edit::ChangeObjInfo(point, self[point][k].id, ObjectInfo::Fire),
                                 ));
                             }
                         }
@@ -508,37 +718,49 @@ impl World {
                     let mut j = 0;
                     while j < self[point].len() {
                         let mut do_remove = false;
-                        if match self[point][j].obj_type {
+                        if matches!(
+                            self[point][j].obj_type,
                             ObjectInfo::Box
-                            | ObjectInfo::Cat
-                            | ObjectInfo::Goal
-                            | ObjectInfo::Water => true,
-                            _ => false,
-                        } {
+                                | ObjectInfo::Cat
+                                | ObjectInfo::Goal
+                                | ObjectInfo::Water
+                        ) {
                             for end in ends.iter() {
                                 if self[*end]
                                     .iter()
                                     .any(|v| matches!(v.obj_type, ObjectInfo::Portal(_, true, _)))
                                     && self[*end].iter().all(|v| {
-                                        v.test_push_by(&self[point][j].obj_type)
+                                        v.test_push_by(&self[point][j].obj_type, direction)
                                             == MoveType::MoveOver
                                     })
                                 {
                                     do_remove = true;
-                                    self.summon_object(*end, self[point][j].obj_type.clone());
-                                    self.edit_history.push((
-                                        self.move_id,
-                                        Edit::SummonObject(*end, self[*end].len() - 1),
-                                    ));
-                                    let mut proposal = [false; 8];
+                                    let id =
+                                        self.summon_object(*end, self[point][j].obj_type.clone());
+                                    self.edit_history
+                                        .push((self.move_id, Edit::SummonObject(*end, id)));
+                                    // Cats keep their keys when they go through
+                                    let held: Vec<u32> =
+                                        self.keys_held(self[point][j].id).collect();
+                                    for color in held {
+                                        self.give_key(id, color);
+                                    }
+                                    self.replace_cat(self[point][j].id, id);
+                                    let mut proposal = vec![false; self[*end].len()];
                                     proposal[self[*end].len() - 1] = true;
                                     self.try_movement(direction, *end, proposal);
                                 }
                             }
                             if do_remove {
-                                let item = self[point].remove(j).obj_type;
-                                self.edit_history
-                                    .push((self.move_id, Edit::DeleteObject(point, j, item)));
+                                let item = self[point].remove(j);
+                                self.edit_history.push((
+                                    self.move_id,
+                                    Edit::DeleteObject(point, j, item.id, item.obj_type),
+                                ));
+                                let held: Vec<u32> = self.keys_held(item.id).collect();
+                                for color in held {
+                                    self.take_key(item.id, color);
+                                }
                             }
                         }
                         if !do_remove {
@@ -566,6 +788,7 @@ impl World {
         self.wiring[wire_loc][wiring_idx] = active;
         let new_wiring = self.wiring[wire_loc];
         for i in 0..self[point].len() {
+            let id = self[point][i].id;
             match self[point][i].obj_type {
                 ObjectInfo::Door(dir, ref mut open) => {
                     let old_open = *open;
@@ -573,10 +796,10 @@ impl World {
                     if *open != old_open {
                         self.edit_history.push((
                             move_id,
-                            Edit::ChangeObjInfo(point, i, ObjectInfo::Door(dir, old_open)),
+                            Edit::ChangeObjInfo(point, id, ObjectInfo::Door(dir, old_open)),
                         ));
                         if self.win_state != WinState::ConstructingLevel {
-                            audio::play("door");
+                            self.events.push(WorldEvent::Sound(Sound::Door));
                         }
                         self.set_animation(point, i, if old_open { 0 } else { 2 }, 5);
                     }
@@ -590,7 +813,7 @@ impl World {
                             move_id,
                             Edit::ChangeObjInfo(
                                 point,
-                                i,
+                                id,
                                 ObjectInfo::RotateableConveyor(dir1, dir2, old_on),
                             ),
                         ));
@@ -605,12 +828,26 @@ impl World {
                             move_id,
                             Edit::ChangeObjInfo(
                                 point,
-                                i,
+                                id,
                                 ObjectInfo::ToggleableConveyor(dir, old_on),
                             ),
                         ));
                     }
                 }
+                ObjectInfo::RotateableMirror(slash, ref mut on) => {
+                    let old_on = *on;
+                    *on = new_wiring.iter().fold(false, |a, b| a ^ b);
+                    if *on != old_on {
+                        self.edit_history.push((
+                            move_id,
+                            Edit::ChangeObjInfo(
+                                point,
+                                id,
+                                ObjectInfo::RotateableMirror(slash, old_on),
+                            ),
+                        ));
+                    }
+                }
                 ObjectInfo::Portal(ref connections, ref mut on, color) => {
                     let old_on = *on;
                     *on = new_wiring.iter().fold(false, |a, b| a ^ b);
@@ -620,7 +857,7 @@ impl World {
                             move_id,
                             Edit::ChangeObjInfo(
                                 point,
-                                i,
+                                id,
                                 ObjectInfo::Portal(connections, old_on, color),
                             ),
                         ));
@@ -629,22 +866,61 @@ impl World {
                 _ => {}
             }
         }
-        return true;
+        true
+    }
+    /// Drop the first box in the cell into an open pit there, filling it in
+    fn fill_pit(&mut self, point: Point) {
+        let Some(pit) = self[point]
+            .iter()
+            .position(|v| v.obj_type == ObjectInfo::Pit(false))
+        else {
+            return;
+        };
+        let Some(i) = self[point]
+            .iter()
+            .position(|v| v.obj_type == ObjectInfo::Box)
+        else {
+            return;
+        };
+        self[point][pit].obj_type = ObjectInfo::Pit(true);
+        self.edit_history.push((
+            self.move_id,
+            Edit::ChangeObjInfo(point, self[point][pit].id, ObjectInfo::Pit(false)),
+        ));
+        let swallowed = self[point].remove(i);
+        self.edit_history.push((
+            self.move_id,
+            Edit::DeleteObject(point, i, swallowed.id, swallowed.obj_type),
+        ));
     }
     /// Set the animation to the given value with given duration and log animation in history
     pub fn set_animation(&mut self, point: Point, idx: usize, anim: i32, duration: usize) {
         let old = self[point][idx].animation.end;
-        self.edit_history
-            .push((self.move_id, Edit::SetAnimation(point, idx, old)));
+        self.edit_history.push((
+            self.move_id,
+            Edit::SetAnimation(point, self[point][idx].id, old),
+        ));
+        let now = self.tick;
         self[point][idx].animation.set_duration(duration);
-        self[point][idx].animation.set(anim);
+        self[point][idx].animation.set(anim, now);
+    }
+    /// Kill the cats with the given death state
+    pub fn die(&mut self, state: WinState) {
+        match state {
+            WinState::Acid => self.events.push(WorldEvent::Sound(Sound::AcidBubbles)),
+            WinState::Burnt | WinState::Lasered => self.events.push(WorldEvent::Sound(Sound::Fire)),
+            _ => {}
+        }
+        self.events.push(WorldEvent::Sound(Sound::Meow));
+        self.events.push(WorldEvent::Died(state));
+        self.win_state = state;
     }
     pub fn _print_state(&self) {
         for y in 0..self.height {
             let mut line: String = String::from("[");
             for x in 0..self.width {
                 let vec: &Vec<Object> = &self[(x, y).into()];
-                if vec.len() == 0 {
+                if vec.is_empty() {
                     line.write_str("Void").unwrap();
                 } else if vec.len() == 1 {
                     line.write_fmt(format_args!("{:?}", vec[0].obj_type))
@@ -667,18 +943,14 @@ impl World {
 
 impl IndexMut<Point> for World {
     fn index_mut(&mut self, index: Point) -> &mut Self::Output {
-        if !self.point_inside(index) {
-            turbo::log!("Error accessing point at {:?}", index);
-        }
-        &mut self.inner[index.y() as usize * self.width + index.x() as usize]
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("Point {:?} is outside the world", index))
     }
 }
 impl Index<Point> for World {
     type Output = Vec<Object>;
     fn index(&self, index: Point) -> &Self::Output {
-        if !self.point_inside(index) {
-            turbo::log!("Error accessing point at {:?}", index);
-        }
-        &self.inner[index.y() as usize * self.width + index.x() as usize]
+        self.get(index)
+            .unwrap_or_else(|| panic!("Point {:?} is outside the world", index))
     }
 }